@@ -1,6 +1,16 @@
+// the theme follows the app, the page fill stays transparent to show the background of the app
+#let dark = sys.inputs.at("theme", default: "light") == "dark"
 #set page(paper:"a4", margin: (x: 8pt, y: 8pt), fill: none)
+#set text(fill: if dark { luma(90%) } else { black })
 #let myblock = block.with(inset: 1em)
 
+// the language is selected in the app and passed in as input
+#let lang = sys.inputs.at("lang", default: "en")
+#set text(lang: lang)
+
+// the variant is selected in the app, e.g. to leave out blocks in a short cv
+#let variant = sys.inputs.at("variant", default: "full")
+
 // #block(inset:1em)[
 #myblock[
 #set text(bottom-edge: "descender")
@@ -1,65 +1,520 @@
-use crate::{document::DocumentPage, overlay::Overlay};
-use egui::{CentralPanel, Color32, ColorImage, Context, Pos2, Rect, Ui, Vec2, Visuals};
+use std::sync::Arc;
+
+use egui::{CentralPanel, Color32, Context, Visuals};
+#[cfg(target_arch = "wasm32")]
+use living_cv::files::Embedded;
+#[cfg(not(target_arch = "wasm32"))]
+use living_cv::files::Filesystem;
+use living_cv::{
+    CvView,
+    document::SourcePosition,
+    editor::SourceEditor,
+    files::FileProvider,
+    popup::{block_section, languages, localize, set_items, variants},
+    qr::{self, QrCodes},
+    timeline,
+};
 
 pub struct App {
-    areas: Vec<Overlay>,
-    texture: egui::TextureHandle,
-    canvas_size: Vec2,
-    document: DocumentPage,
-    recompile_needed: bool,
+    cv: CvView,
+    /// The popup data file, with the edits of the authoring mode.
+    popup_file: toml::Table,
+    popup_file_changed: bool,
+    /// Why the popup data file could not be read, it is not overwritten then.
+    popup_file_error: Option<String>,
+    authoring: bool,
+    variant: String,
+    variants: Vec<String>,
+    language: String,
+    languages: Vec<String>,
+    theme_preference: egui::ThemePreference,
+    reduced_motion: bool,
+    status: Option<String>,
+    show_timeline: bool,
+    /// The source editor, only available in the developer mode.
+    editor: Option<SourceEditor>,
+    show_editor: bool,
+    /// The source position of the last click on the page in the developer mode.
+    source_click: Option<SourcePosition>,
+    #[cfg(target_arch = "wasm32")]
+    fragment_block: Option<String>,
 }
 
 impl App {
-    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
-        cc.egui_ctx.set_visuals(Visuals {
-            dark_mode: false,
-            panel_fill: Color32::WHITE,
-            ..Default::default()
-        });
+    /// Creates the app, `open` is the label of a block whose popup is opened on startup.
+    pub fn new(cc: &eframe::CreationContext<'_>, open: Option<String>, system_fonts: bool) -> Self {
+        cc.egui_ctx.set_visuals_of(
+            egui::Theme::Light,
+            Visuals {
+                panel_fill: Color32::WHITE,
+                ..Visuals::light()
+            },
+        );
+        cc.egui_ctx.set_visuals_of(
+            egui::Theme::Dark,
+            Visuals {
+                panel_fill: Color32::from_gray(24),
+                ..Visuals::dark()
+            },
+        );
+
+        // restore the stored theme choice, the document follows the resulting theme
+        let theme_preference = cc
+            .storage
+            .and_then(|storage| storage.get_string("theme"))
+            .and_then(|theme| parse_theme_preference(&theme))
+            .unwrap_or_default();
+        cc.egui_ctx.set_theme(theme_preference);
+
+        let reduced_motion = cc
+            .storage
+            .and_then(|storage| storage.get_string("reduced_motion"))
+            .map(|value| value == "true")
+            .unwrap_or_else(prefers_reduced_motion);
+        set_reduced_motion(&cc.egui_ctx, reduced_motion);
+
+        let (popup_file, popup_file_error) = popup_file();
+        let variants = variants(Some(&popup_file));
+        let variant = initial_variant(cc, &variants);
+        let languages = languages(Some(&popup_file));
+        let language = initial_language(cc, &languages);
+
+        // the developer mode is turned on with `--dev` on native and `?dev` on the web
+        #[cfg(not(target_arch = "wasm32"))]
+        let developer_mode = std::env::args().any(|arg| arg == "--dev");
+        #[cfg(target_arch = "wasm32")]
+        let developer_mode = cc
+            .integration_info
+            .web_info
+            .location
+            .query_map
+            .contains_key("dev");
+
+        let (source, files) = document_files();
+        let files = with_qr_codes(files, &popup_file);
+        let editor = developer_mode.then(|| source_editor(&source, files.clone()));
+        let source = editor
+            .as_ref()
+            .map_or(source.as_str(), |editor| editor.source());
+        let mut cv = CvView::new(source, files)
+            .source_clicks(developer_mode)
+            .system_fonts(system_fonts)
+            .inputs(&document_inputs(&variant, &language))
+            .popup_data(localize(popup_file.clone(), &variant, &language))
+            .image(
+                "header.jpg",
+                egui::include_image!("../assets/images/header.jpg"),
+            );
+        if let Some(label) = &open {
+            cv.open_block(label);
+        }
 
         Self {
-            areas: Vec::new(),
-            texture: cc.egui_ctx.load_texture(
-                "background",
-                egui::ColorImage::example(),
-                egui::TextureOptions::NEAREST,
-            ),
-            canvas_size: Vec2::default(),
-            document: get_document(cc.egui_ctx.screen_rect().size())
-                .expect("Error with the typst document"),
-            recompile_needed: true,
+            cv,
+            popup_file,
+            popup_file_changed: false,
+            popup_file_error,
+            authoring: false,
+            variant,
+            variants,
+            language,
+            languages,
+            theme_preference,
+            reduced_motion,
+            status: None,
+            show_timeline: false,
+            show_editor: editor.is_some(),
+            source_click: None,
+            editor,
+            #[cfg(target_arch = "wasm32")]
+            fragment_block: open,
+        }
+    }
+
+    /// Passes the chosen variant and language to the cv.
+    fn update_settings(&mut self) {
+        self.cv
+            .set_inputs(&document_inputs(&self.variant, &self.language));
+        let popup_data = localize(self.popup_file.clone(), &self.variant, &self.language);
+        self.cv.set_popup_data(Some(popup_data));
+    }
+
+    /// Exports the QR code of the open block, or of the whole cv if no block is open.
+    fn export_qr_code(&mut self) {
+        let label = self.cv.open_block_label().unwrap_or_default();
+        self.status = qr::cv_url(Some(&self.popup_file))
+            .ok_or("No url for the QR code, set `url` in the popup data".to_string())
+            .and_then(|url| qr::svg(&qr::block_url(&url, &label)))
+            .and_then(|svg| crate::export::save_file("cv-qr.svg", "image/svg+xml", svg.as_bytes()))
+            .err();
+    }
+
+    /// Writes the edited popup items to the section of the popup data file they come from.
+    fn apply_popup_edits(&mut self) {
+        for (label, items) in self.cv.take_popup_edits() {
+            let section =
+                block_section(&mut self.popup_file, &label, &self.variant, &self.language);
+            set_items(section, &label, &items);
+            self.popup_file_changed = true;
         }
     }
 
-    fn recompile(&mut self) {
-        // get the document
-        self.document = get_document(self.canvas_size).expect("Error with typst document");
+    /// Writes the popup data file back to [`POPUP_FILE`] on native, on the web it is downloaded.
+    ///
+    /// The file is written by the toml serializer, so comments and the formatting are not kept.
+    fn save_popup_file(&mut self) {
+        let result = toml::to_string_pretty(&self.popup_file)
+            .map_err(|e| format!("Could not write the popup data: {e}"))
+            .and_then(|text| {
+                #[cfg(not(target_arch = "wasm32"))]
+                {
+                    std::fs::write(POPUP_FILE, text)
+                        .map_err(|e| format!("Could not write {POPUP_FILE}: {e}"))
+                }
+
+                #[cfg(target_arch = "wasm32")]
+                {
+                    crate::export::save_file("popup.toml", "application/toml", text.as_bytes())
+                }
+            });
 
-        // clear the stored data blocks
-        self.areas.clear();
+        self.popup_file_changed &= result.is_err();
+        self.status = result.err();
+    }
 
-        // analyze the document
-        let blocks = self.document.get_data_blocks();
-        for block in &blocks {
-            let mut final_rect = Rect::from_pos(Pos2::new(block.x, block.y));
-            final_rect.set_width(block.width);
-            final_rect.set_height(block.height);
+    /// Exports the document as pdf, with the edits of the developer mode.
+    fn export_pdf(&mut self) {
+        self.status = self
+            .cv
+            .pdf()
+            .and_then(|pdf| crate::export::save_file("cv.pdf", "application/pdf", &pdf))
+            .err();
+    }
 
-            let new_area = Overlay::new(final_rect, block.label.clone(), self.canvas_size);
-            self.areas.push(new_area);
+    /// Keeps the url fragment and the open popup in sync in both directions.
+    #[cfg(target_arch = "wasm32")]
+    fn sync_fragment(&mut self) {
+        // the fragment changed in the browser, e.g. with the back button
+        let fragment_block = crate::deep_link::current_fragment();
+        if fragment_block != self.fragment_block {
+            self.cv.close_popups();
+            if let Some(label) = &fragment_block {
+                self.cv.open_block(label);
+            }
+            self.fragment_block = fragment_block;
+            return;
         }
 
-        // reset the flag
-        self.recompile_needed = false;
+        let open_block = self.cv.open_block_label();
+        if open_block != self.fragment_block && !self.cv.is_opening_block() {
+            crate::deep_link::set_fragment(open_block.as_deref());
+            self.fragment_block = open_block;
+        }
+    }
+
+    /// Small floating toolbar in the top right corner with the document settings.
+    fn show_toolbar(&mut self, ctx: &Context) {
+        egui::Area::new(egui::Id::new("toolbar"))
+            .anchor(egui::Align2::RIGHT_TOP, [-8., 8.])
+            .order(egui::Order::Foreground)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        if ui.button("Fit").on_hover_text("Zoom to fit (0)").clicked() {
+                            self.cv.zoom_to_fit();
+                        }
+                        if ui.button("100%").on_hover_text("Actual size (1)").clicked() {
+                            self.cv.zoom_actual_size();
+                        }
+
+                        ui.separator();
+                        if ui.button("PDF").on_hover_text("Download PDF").clicked() {
+                            self.export_pdf();
+                        }
+                        if ui
+                            .button("QR")
+                            .on_hover_text("Download a QR code of the cv or of the open block")
+                            .clicked()
+                        {
+                            self.export_qr_code();
+                        }
+
+                        if self.editor.is_some() {
+                            ui.toggle_value(&mut self.show_editor, "Source")
+                                .on_hover_text("Edit the typst source");
+                            if ui
+                                .toggle_value(&mut self.authoring, "Popups")
+                                .on_hover_text("Edit the items of the popups")
+                                .changed()
+                            {
+                                self.cv.set_authoring(self.authoring);
+                            }
+                            if self.popup_file_changed
+                                && ui
+                                    .add_enabled(
+                                        self.popup_file_error.is_none(),
+                                        egui::Button::new("Save popups"),
+                                    )
+                                    .on_hover_text("Save the edited popup data")
+                                    .on_disabled_hover_text(
+                                        "The popup data file could not be read, fix it first",
+                                    )
+                                    .clicked()
+                            {
+                                self.save_popup_file();
+                            }
+                        }
+
+                        if !self.cv.timeline().is_empty() {
+                            ui.toggle_value(&mut self.show_timeline, "Timeline")
+                                .on_hover_text("Show the dated blocks on a timeline");
+                        }
+
+                        if self.variants.len() > 1 {
+                            ui.separator();
+
+                            let mut variant = self.variant.clone();
+                            egui::ComboBox::from_id_salt("variant")
+                                .selected_text(&variant)
+                                .show_ui(ui, |ui| {
+                                    for option in &self.variants {
+                                        ui.selectable_value(&mut variant, option.clone(), option);
+                                    }
+                                })
+                                .response
+                                .on_hover_text("CV variant");
+                            if variant != self.variant {
+                                self.variant = variant;
+                                self.update_settings();
+                            }
+                        }
+
+                        if self.languages.len() > 1 {
+                            ui.separator();
+
+                            let mut language = self.language.clone();
+                            for option in &self.languages {
+                                ui.selectable_value(
+                                    &mut language,
+                                    option.clone(),
+                                    option.to_uppercase(),
+                                );
+                            }
+                            if language != self.language {
+                                self.language = language;
+                                self.update_settings();
+                            }
+                        }
+
+                        // the document is compiled again once the resulting theme changed
+                        ui.separator();
+                        let mut theme_preference = ctx.options(|o| o.theme_preference);
+                        theme_preference.radio_buttons(ui);
+                        ctx.set_theme(theme_preference);
+
+                        ui.separator();
+                        if ui
+                            .checkbox(&mut self.reduced_motion, "Reduce motion")
+                            .on_hover_text("Turn off fades and highlights")
+                            .changed()
+                        {
+                            set_reduced_motion(ctx, self.reduced_motion);
+                        }
+                    });
+
+                    if let Some(position) = &self.source_click
+                        && let Some(editor) = &self.editor
+                    {
+                        let path = position.path.as_deref().unwrap_or(editor.main_path());
+                        ui.label(format!(
+                            "Source: {}:{}:{}",
+                            path.display(),
+                            position.line + 1,
+                            position.column + 1
+                        ));
+                    }
+
+                    // a failed compilation keeps the previous document, missing fonts use a fallback
+                    let error_color = ui.visuals().error_fg_color;
+                    if let Some(status) = self
+                        .status
+                        .clone()
+                        .or_else(|| self.cv.error())
+                        .or_else(|| self.popup_file_error.clone())
+                    {
+                        ui.colored_label(error_color, status);
+                    }
+                    if !self.cv.font_fallbacks().is_empty() {
+                        ui.colored_label(
+                            error_color,
+                            format!(
+                                "Fonts not available, using a fallback: {}",
+                                self.cv.font_fallbacks().join(", ")
+                            ),
+                        );
+                    }
+                });
+            });
     }
 }
 
-fn get_document(available_size: Vec2) -> Result<DocumentPage, String> {
+/// The variant from the `?variant=` url parameter on the web, then the stored one, then the first.
+fn initial_variant(cc: &eframe::CreationContext<'_>, variants: &[String]) -> String {
+    let supported = |variant: &str| variants.iter().any(|v| v == variant);
+
+    #[cfg(target_arch = "wasm32")]
+    if let Some(variant) = cc
+        .integration_info
+        .web_info
+        .location
+        .query_map
+        .get("variant")
+        .and_then(|values| values.first())
+        && supported(variant)
+    {
+        return variant.clone();
+    }
+
+    if let Some(variant) = cc.storage.and_then(|storage| storage.get_string("variant"))
+        && supported(&variant)
+    {
+        return variant;
+    }
+
+    variants.first().cloned().unwrap_or_default()
+}
+
+/// Picks the language from the url (`?lang=de`), the stored choice or the browser locale,
+/// falling back to the first language of the popup data.
+fn initial_language(cc: &eframe::CreationContext<'_>, languages: &[String]) -> String {
+    let supported = |language: &str| languages.iter().any(|l| l == language);
+
+    #[cfg(target_arch = "wasm32")]
+    if let Some(language) = cc
+        .integration_info
+        .web_info
+        .location
+        .query_map
+        .get("lang")
+        .and_then(|values| values.first())
+        && supported(language)
+    {
+        return language.clone();
+    }
+
+    if let Some(language) = cc
+        .storage
+        .and_then(|storage| storage.get_string("language"))
+        && supported(&language)
+    {
+        return language;
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    if let Some(locale) = web_sys::window().and_then(|window| window.navigator().language()) {
+        let language = locale.split('-').next().unwrap_or_default().to_lowercase();
+        if supported(&language) {
+            return language;
+        }
+    }
+
+    languages.first().cloned().unwrap_or_default()
+}
+
+/// Turns the animations of egui off, the overlays and popups use the same animation time.
+fn set_reduced_motion(ctx: &Context, reduced_motion: bool) {
+    let animation_time = if reduced_motion {
+        0.
+    } else {
+        egui::Style::default().animation_time
+    };
+    ctx.all_styles_mut(|style| style.animation_time = animation_time);
+}
+
+/// Whether the browser asks for reduced motion, there is no such setting on native.
+#[cfg(not(target_arch = "wasm32"))]
+fn prefers_reduced_motion() -> bool {
+    false
+}
+
+/// Whether the browser asks for reduced motion, there is no such setting on native.
+#[cfg(target_arch = "wasm32")]
+fn prefers_reduced_motion() -> bool {
+    web_sys::window()
+        .and_then(|window| window.match_media("(prefers-reduced-motion: reduce)").ok())
+        .flatten()
+        .is_some_and(|query| query.matches())
+}
+
+fn parse_theme_preference(value: &str) -> Option<egui::ThemePreference> {
+    match value {
+        "dark" => Some(egui::ThemePreference::Dark),
+        "light" => Some(egui::ThemePreference::Light),
+        "system" => Some(egui::ThemePreference::System),
+        _ => None,
+    }
+}
+
+/// The values passed to typst as `sys.inputs`, the theme is added by the view.
+fn document_inputs<'a>(variant: &'a str, language: &'a str) -> Vec<(&'a str, &'a str)> {
+    let mut inputs = Vec::new();
+    if !variant.is_empty() {
+        inputs.push(("variant", variant));
+    }
+    if !language.is_empty() {
+        inputs.push(("lang", language));
+    }
+    inputs
+}
+
+/// The popup data file on native, relative to the working directory like `assets`. The popup
+/// edits are saved there.
+#[cfg(not(target_arch = "wasm32"))]
+pub const POPUP_FILE: &str = "popup.toml";
+
+/// Reads the popup data file, without the variant and language applied.
+///
+/// On native the file is read at runtime, so saved edits are picked up without a rebuild, the
+/// compiled in copy is the fallback. The web always uses the compiled in copy. Also returns the
+/// error if the file exists but could not be parsed.
+pub fn popup_file() -> (toml::Table, Option<String>) {
+    let compiled_in = || include_str!("../popup.toml").parse().unwrap_or_default();
+
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Ok(text) = std::fs::read_to_string(POPUP_FILE) {
+        return match text.parse() {
+            Ok(table) => (table, None),
+            Err(e) => (
+                compiled_in(),
+                Some(format!(
+                    "Could not read {POPUP_FILE}, using the built-in copy: {e}"
+                )),
+            ),
+        };
+    }
+
+    (compiled_in(), None)
+}
+
+/// The main typst file of the cv.
+#[cfg(not(target_arch = "wasm32"))]
+pub const MAIN_SOURCE: &str = std::include_str!("../assets/cv.typ");
+
+/// The main source of the document and the files next to it.
+///
+/// On native the files are read from `assets` at runtime, so an edited or imported `cv.typ` is
+/// picked up without a rebuild, the compiled in [`MAIN_SOURCE`] is the fallback. On the web,
+/// everything is embedded from `assets/cv`.
+pub fn document_files() -> (String, Arc<dyn FileProvider>) {
     #[cfg(not(target_arch = "wasm32"))]
     {
-        let content = std::include_str!("../assets/cv.typ");
-        let document = DocumentPage::new(content, available_size)?;
-        Ok(document)
+        let files = Filesystem::new("./assets/");
+        let source = files
+            .read(std::path::Path::new("cv.typ"))
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes.to_vec()).ok())
+            .unwrap_or_else(|| MAIN_SOURCE.to_string());
+        (source, Arc::new(files))
     }
 
     #[cfg(target_arch = "wasm32")]
@@ -67,29 +522,39 @@ fn get_document(available_size: Vec2) -> Result<DocumentPage, String> {
         let content_dir = include_dir::include_dir!("./assets/cv/");
         let content_main_file = content_dir.get_file("main.typ").unwrap();
         let content = content_main_file.contents_utf8().unwrap();
-        let document = DocumentPage::new(content, available_size, content_dir)?;
-        Ok(document)
+        (content.to_string(), Arc::new(Embedded::new(content_dir)))
     }
 }
 
-fn render_background(
-    ui: &mut Ui,
-    document: &DocumentPage,
-    texture_handle: &mut egui::TextureHandle,
-) {
-    let final_img = ColorImage::from_rgba_unmultiplied(
-        [
-            document.image.width as usize,
-            document.image.height as usize,
-        ],
-        document.as_vec(),
-    );
-
-    // set the background image derived from the typst document
-    texture_handle.set(final_img, egui::TextureOptions::NEAREST);
-    let size = texture_handle.size_vec2();
-    let sized_texture = egui::load::SizedTexture::new(texture_handle, size);
-    ui.add(egui::Image::new(sized_texture));
+/// The editor of the developer mode, on native it edits and saves the files in `assets`.
+fn source_editor(source: &str, files: Arc<dyn FileProvider>) -> SourceEditor {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        SourceEditor::new("cv.typ", source, files).save_to("./assets/")
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    {
+        SourceEditor::new("main.typ", source, files)
+    }
+}
+
+/// Adds the QR codes of the cv to the files, if the url of the cv is known.
+fn with_qr_codes(files: Arc<dyn FileProvider>, popup_file: &toml::Table) -> Arc<dyn FileProvider> {
+    match qr::cv_url(Some(popup_file)) {
+        Some(url) => Arc::new(QrCodes::new(url, files)),
+        None => files,
+    }
+}
+
+/// Whether the document uses the system fonts, they can be turned off in the popup data for the
+/// same output on every machine.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn system_fonts(popup_file: &toml::Table) -> bool {
+    popup_file
+        .get("system_fonts")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(true)
 }
 
 impl eframe::App for App {
@@ -97,20 +562,57 @@ impl eframe::App for App {
         [0.0, 0.0, 0.0, 0.0]
     }
 
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        storage.set_string("variant", self.variant.clone());
+        storage.set_string("language", self.language.clone());
+
+        let theme = match self.theme_preference {
+            egui::ThemePreference::Dark => "dark",
+            egui::ThemePreference::Light => "light",
+            egui::ThemePreference::System => "system",
+        };
+        storage.set_string("theme", theme.to_string());
+        storage.set_string("reduced_motion", self.reduced_motion.to_string());
+    }
+
     fn update(&mut self, ctx: &Context, _: &mut eframe::Frame) {
-        // store the canvas size and retrigger the document compilation and area detection if it changed
-        let size = ctx.screen_rect().size();
-        if size != self.canvas_size {
-            self.recompile_needed = true;
-            self.canvas_size = size;
+        // the docked popup has to be added before the central panel and takes its space from the CV
+        self.cv.show_side_panel(ctx);
+
+        // the timeline is docked at the bottom, a clicked bar opens its block like a deep link
+        if self.show_timeline && !self.cv.timeline().is_empty() {
+            let clicked = egui::TopBottomPanel::bottom("timeline")
+                .resizable(true)
+                .show(ctx, |ui| {
+                    egui::ScrollArea::vertical()
+                        .show(ui, |ui| timeline::show(ui, self.cv.timeline()))
+                        .inner
+                })
+                .inner;
+            if let Some(label) = clicked {
+                self.cv.open_block(&label);
+            }
         }
 
+        // the editor takes the left half of the window by default
+        if self.show_editor
+            && let Some(editor) = &mut self.editor
+        {
+            egui::SidePanel::left("source_editor")
+                .resizable(true)
+                .default_width(ctx.screen_rect().width() / 2.)
+                .show(ctx, |ui| editor.show(ui, &mut self.cv));
+        }
+
+        self.theme_preference = ctx.options(|o| o.theme_preference);
+
         CentralPanel::default()
             .frame(egui::Frame::default().inner_margin(0.0).outer_margin(0.0))
             .show(ctx, |ui| {
+                // q is free for typing while the source editor has the focus
                 #[cfg(not(target_arch = "wasm32"))]
                 ui.input(|i| {
-                    if i.key_pressed(egui::Key::Q) {
+                    if i.key_pressed(egui::Key::Q) && !ctx.wants_keyboard_input() {
                         let ctx = ctx.clone();
                         std::thread::spawn(move || {
                             ctx.send_viewport_cmd(egui::ViewportCommand::Close);
@@ -118,18 +620,34 @@ impl eframe::App for App {
                     }
                 });
 
-                // only recompile and analyze the document on start and when the screen area changed
-                if self.recompile_needed {
-                    self.recompile();
-                }
+                self.cv.show(ui);
+            });
+        self.apply_popup_edits();
 
-                // draw the document as a texture in the background
-                render_background(ui, &self.document, &mut self.texture);
+        // "Save contact" in a popup saves the contact details as a vCard
+        if let Some(contact) = self.cv.take_saved_contact() {
+            self.status = crate::export::save_file(
+                &contact.file_name(),
+                "text/vcard",
+                contact.to_vcard().as_bytes(),
+            )
+            .err();
+        }
 
-                // check for hovering areas and start the relevant animation
-                for area in &mut self.areas {
-                    ui.add(&mut *area);
-                }
-            });
+        // a click on the page moves the cursor of the editor to the source of the clicked element
+        if let Some(position) = self.cv.take_source_click() {
+            if self.show_editor
+                && let Some(editor) = &mut self.editor
+            {
+                editor.go_to(&position);
+            }
+            self.source_click = Some(position);
+        }
+
+        self.show_toolbar(ctx);
+        self.cv.show_tag_bar(ctx);
+
+        #[cfg(target_arch = "wasm32")]
+        self.sync_fragment();
     }
 }
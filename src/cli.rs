@@ -0,0 +1,333 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use living_cv::{
+    document::{DataBlock, DocumentPage},
+    files::{Archive, FileProvider, Filesystem},
+    qr::{self, QrCodes},
+};
+
+const USAGE: &str = "\
+Usage: living_cv [--open <label>] [--dev]
+       living_cv render [--page <n>] [--dpi <dpi>] [--landscape] [--svg] [<document options>] [--output <path>]
+       living_cv pdf [<document options>] [--output <path>]
+       living_cv blocks [--page <n>] [--landscape] [<document options>] [--output <path>]
+       living_cv qr [--block <label>] [--url <url>] [--output <path>]
+       living_cv import <resume.json> [--root <dir>] [--force]
+
+Without a command the cv is opened in a window, --dev opens it with the source editor.
+
+Commands:
+  render  Render a page as png, or as svg with --svg
+  pdf     Export the document as pdf, always in the portrait layout
+  blocks  Print the detected blocks of a page as json, in page coordinates (pt)
+  qr      Write a QR code linking to the live cv, or to a block of it with --block, as svg
+  import  Generate assets/cv.typ and popup.toml from a JSON Resume file, or both in the --root dir
+
+Options:
+  --page <n>             The page, starting at 1 [default: 1]
+  --dpi <dpi>            Resolution of the png [default: 144]
+  --landscape            Use the landscape layout of the document
+  --svg                  Render an svg instead of a png
+  --block <label>        The block the QR code links to
+  --force                Overwrite existing files when importing
+  --output <path>        The output file, blocks are printed to stdout by default
+
+Document options:
+  --input <key=value>    Value passed to typst as sys.inputs, e.g. variant=short or lang=de
+  --no-system-fonts      Only use the fonts in assets/fonts and the fonts embedded in typst
+  --root <dir>           Compile the cv.typ in the directory instead of the built-in cv
+  --archive <path>       Compile the cv.typ in a tar or tar.gz archive instead of the built-in cv
+  --url <url>            The url of the live cv for the QR codes, instead of the url in the popup data";
+
+struct Options {
+    page: usize,
+    dpi: f32,
+    landscape: bool,
+    svg: bool,
+    inputs: Vec<(String, String)>,
+    system_fonts: bool,
+    root: Option<PathBuf>,
+    archive: Option<PathBuf>,
+    output: Option<PathBuf>,
+    block: String,
+    url: Option<String>,
+    force: bool,
+}
+
+impl Options {
+    fn parse(args: &[String], system_fonts: bool) -> Result<Self, String> {
+        let mut options = Self {
+            page: 1,
+            dpi: 144.,
+            landscape: false,
+            svg: false,
+            inputs: Vec::new(),
+            system_fonts,
+            root: None,
+            archive: None,
+            output: None,
+            block: String::new(),
+            url: None,
+            force: false,
+        };
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("Missing value for {arg}"));
+            match arg.as_str() {
+                "--page" => {
+                    options.page = value()?
+                        .parse()
+                        .ok()
+                        .filter(|page| *page > 0)
+                        .ok_or("The page has to be a number starting at 1")?;
+                }
+                "--dpi" => {
+                    options.dpi = value()?.parse().map_err(|_| "The dpi has to be a number")?;
+                }
+                "--landscape" => options.landscape = true,
+                "--svg" => options.svg = true,
+                "--no-system-fonts" => options.system_fonts = false,
+                "--input" => {
+                    let (key, input) = value()?
+                        .split_once('=')
+                        .ok_or("Inputs have to be given as key=value")?;
+                    options.inputs.push((key.to_string(), input.to_string()));
+                }
+                "--root" => options.root = Some(PathBuf::from(value()?)),
+                "--archive" => options.archive = Some(PathBuf::from(value()?)),
+                "--output" => options.output = Some(PathBuf::from(value()?)),
+                "--block" => options.block = value()?.clone(),
+                "--url" => options.url = Some(value()?.clone()),
+                "--force" => options.force = true,
+                _ => return Err(format!("Unknown argument {arg}")),
+            }
+        }
+
+        Ok(options)
+    }
+
+    fn inputs(&self) -> Vec<(&str, &str)> {
+        self.inputs
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect()
+    }
+
+    /// The url of the live cv, from --url or the popup data.
+    fn cv_url(&self) -> Option<String> {
+        self.url
+            .clone()
+            .or_else(|| qr::cv_url(Some(&crate::app::popup_file().0)))
+    }
+
+    /// The main source and the files of the document with the QR codes, the built-in cv by
+    /// default.
+    fn files(&self) -> Result<(String, Arc<dyn FileProvider>), String> {
+        let (source, files) = self.document_files()?;
+        let files = match self.cv_url() {
+            Some(url) => Arc::new(QrCodes::new(url, files)),
+            None => files,
+        };
+        Ok((source, files))
+    }
+
+    fn document_files(&self) -> Result<(String, Arc<dyn FileProvider>), String> {
+        let files: Arc<dyn FileProvider> = match (&self.root, &self.archive) {
+            (Some(_), Some(_)) => return Err("Use either --root or --archive".to_string()),
+            (Some(root), None) => Arc::new(Filesystem::new(root)),
+            (None, Some(path)) => {
+                let bytes = std::fs::read(path)
+                    .map_err(|e| format!("Could not read {}: {e}", path.display()))?;
+                Arc::new(Archive::new(&bytes)?)
+            }
+            (None, None) => return Ok(crate::app::document_files()),
+        };
+
+        let source = files
+            .read(Path::new("cv.typ"))
+            .map_err(|e| format!("Could not read cv.typ: {e}"))?;
+        let source = std::str::from_utf8(&source).map_err(|_| "cv.typ is not valid utf-8")?;
+        Ok((source.to_string(), files))
+    }
+
+    fn compile(&self) -> Result<typst::layout::PagedDocument, String> {
+        compile(self, self.landscape)
+    }
+
+    fn page<'a>(
+        &self,
+        document: &'a typst::layout::PagedDocument,
+    ) -> Result<&'a typst::layout::Page, String> {
+        document.pages.get(self.page - 1).ok_or(format!(
+            "Page {} does not exist, the document has {} pages",
+            self.page,
+            document.pages.len()
+        ))
+    }
+}
+
+/// Runs the headless command given as first argument, without opening a window.
+///
+/// Returns `None` if the arguments don't start with a command, otherwise the exit code.
+///
+/// `system_fonts` is the default of the document options, `--no-system-fonts` turns them off.
+pub fn run(args: &[String], system_fonts: bool) -> Option<i32> {
+    let (command, args) = args.split_first()?;
+    let result = match command.as_str() {
+        "render" => Options::parse(args, system_fonts).and_then(|options| render(&options)),
+        "pdf" => Options::parse(args, system_fonts).and_then(|options| pdf(&options)),
+        "blocks" => Options::parse(args, system_fonts).and_then(|options| blocks(&options)),
+        "qr" => Options::parse(args, system_fonts).and_then(|options| qr_code(&options)),
+        "import" => match args.split_first() {
+            Some((resume, args)) => Options::parse(args, system_fonts)
+                .and_then(|options| import(Path::new(resume), &options)),
+            None => Err("Missing the resume.json to import".to_string()),
+        },
+        "help" | "--help" | "-h" => {
+            println!("{USAGE}");
+            Ok(())
+        }
+        _ => return None,
+    };
+
+    match result {
+        Ok(()) => Some(0),
+        Err(error) => {
+            eprintln!("Error: {error}\n\n{USAGE}");
+            Some(1)
+        }
+    }
+}
+
+/// Compiles the document and reports the font families that were replaced by a fallback.
+fn compile(options: &Options, landscape: bool) -> Result<typst::layout::PagedDocument, String> {
+    let (source, files) = options.files()?;
+    let (document, font_fallbacks) = DocumentPage::compile(
+        &source,
+        &options.inputs(),
+        files,
+        options.system_fonts,
+        landscape,
+    )?;
+    for family in font_fallbacks {
+        eprintln!("Warning: the font family {family} is not available, a fallback font is used");
+    }
+    Ok(document)
+}
+
+fn render(options: &Options) -> Result<(), String> {
+    let document = options.compile()?;
+    let page = options.page(&document)?;
+
+    let (bytes, extension) = if options.svg {
+        (typst_svg::svg(page).into_bytes(), "svg")
+    } else {
+        let pixmap = typst_render::render(page, options.dpi / 72.);
+        let png = pixmap.encode_png().map_err(|e| e.to_string())?;
+        (png, "png")
+    };
+
+    let default_output = format!("cv-{}.{extension}", options.page);
+    write(options, &default_output, &bytes)
+}
+
+fn pdf(options: &Options) -> Result<(), String> {
+    let document = compile(options, false)?;
+    let pdf = DocumentPage::export_pdf(&document)?;
+    write(options, "cv.pdf", &pdf)
+}
+
+fn blocks(options: &Options) -> Result<(), String> {
+    #[derive(serde::Serialize)]
+    struct PageBlocks {
+        page: usize,
+        width: f64,
+        height: f64,
+        blocks: Vec<DataBlock>,
+    }
+
+    let document = options.compile()?;
+    let page = options.page(&document)?;
+    let size = page.frame.size();
+    let page_blocks = PageBlocks {
+        page: options.page,
+        width: size.x.to_pt(),
+        height: size.y.to_pt(),
+        blocks: DocumentPage::page_blocks(page),
+    };
+
+    let json = serde_json::to_string_pretty(&page_blocks).map_err(|e| e.to_string())?;
+    match &options.output {
+        Some(_) => write(options, "", json.as_bytes()),
+        None => {
+            println!("{json}");
+            Ok(())
+        }
+    }
+}
+
+fn qr_code(options: &Options) -> Result<(), String> {
+    let url = options
+        .cv_url()
+        .ok_or("No url for the QR code, set `url` in the popup data or use --url")?;
+    let svg = qr::svg(&qr::block_url(&url, &options.block))?;
+    let default_output = if options.block.is_empty() {
+        "cv-qr.svg".to_string()
+    } else {
+        format!("cv-qr-{}.svg", options.block)
+    };
+    write(options, &default_output, svg.as_bytes())
+}
+
+/// Writes the cv generated from a JSON Resume file: the source to the asset root and the popup
+/// data to where the app reads it, or both into the --root directory.
+fn import(resume: &Path, options: &Options) -> Result<(), String> {
+    let json = std::fs::read_to_string(resume)
+        .map_err(|e| format!("Could not read {}: {e}", resume.display()))?;
+    let cv = living_cv::resume::import(&json)?;
+    let popup_data = toml::to_string_pretty(&cv.popup_data)
+        .map_err(|e| format!("Could not write the popup data: {e}"))?;
+
+    let (source_path, popup_path) = match &options.root {
+        Some(root) => (root.join("cv.typ"), root.join("popup.toml")),
+        None => (
+            PathBuf::from("assets/cv.typ"),
+            PathBuf::from(crate::app::POPUP_FILE),
+        ),
+    };
+    if !options.force
+        && let Some(path) = [&source_path, &popup_path]
+            .into_iter()
+            .find(|path| path.exists())
+    {
+        return Err(format!(
+            "{} exists already, use --force to overwrite it",
+            path.display()
+        ));
+    }
+
+    for (path, text) in [(&source_path, &cv.source), (&popup_path, &popup_data)] {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| format!("Could not create {}: {e}", dir.display()))?;
+        }
+        std::fs::write(path, text)
+            .map_err(|e| format!("Could not write {}: {e}", path.display()))?;
+        eprintln!("Written to {}", path.display());
+    }
+    Ok(())
+}
+
+fn write(options: &Options, default_output: &str, bytes: &[u8]) -> Result<(), String> {
+    let path = options
+        .output
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(default_output));
+    std::fs::write(&path, bytes).map_err(|e| format!("Could not write {}: {e}", path.display()))?;
+    eprintln!("Written to {}", path.display());
+    Ok(())
+}
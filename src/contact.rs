@@ -0,0 +1,227 @@
+/// Contact details of a block, which can be saved as a vCard.
+///
+/// The details are given in a `contact` table of the block in the popup data or in a dictionary
+/// in the typst document like `#metadata((contact: (name: "Jane Doe"))) <contact>`. `email`,
+/// `phone` and `url` can be lists.
+#[derive(Clone, Debug, Default)]
+pub struct Contact {
+    pub name: String,
+    pub title: Option<String>,
+    pub organization: Option<String>,
+    pub emails: Vec<String>,
+    pub phones: Vec<String>,
+    pub urls: Vec<String>,
+    pub address: Option<String>,
+    pub note: Option<String>,
+}
+
+/// Reads the contact details of a block, the typst metadata takes precedence over the popup data.
+pub fn block_contact(
+    popup_data: Option<&toml::Table>,
+    metadata: Option<&toml::Value>,
+    label: &str,
+) -> Option<Contact> {
+    let table = metadata
+        .and_then(|metadata| metadata.get(label))
+        .or_else(|| {
+            popup_data
+                .and_then(|data| data.get(label))
+                .and_then(|block| block.get("contact"))
+        })?
+        .as_table()?;
+    Contact::from_table(table)
+}
+
+impl Contact {
+    /// Reads the details from a table, a name is required.
+    pub fn from_table(table: &toml::Table) -> Option<Self> {
+        let text = |key: &str| table.get(key)?.as_str().map(String::from);
+        let list = |key: &str| match table.get(key) {
+            Some(toml::Value::String(value)) => vec![value.clone()],
+            Some(toml::Value::Array(values)) => values
+                .iter()
+                .filter_map(|value| value.as_str().map(String::from))
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        Some(Self {
+            name: text("name").filter(|name| !name.trim().is_empty())?,
+            title: text("title"),
+            organization: text("organization"),
+            emails: list("email"),
+            phones: list("phone"),
+            urls: list("url"),
+            address: text("address"),
+            note: text("note"),
+        })
+    }
+
+    /// The name of the vCard file, e.g. `jane-doe.vcf`.
+    pub fn file_name(&self) -> String {
+        let name: String = self
+            .name
+            .split_whitespace()
+            .map(|word| {
+                word.chars()
+                    .filter(|c| c.is_alphanumeric() || *c == '-')
+                    .collect::<String>()
+                    .to_lowercase()
+            })
+            .filter(|word| !word.is_empty())
+            .collect::<Vec<_>>()
+            .join("-");
+        if name.is_empty() {
+            "contact.vcf".to_string()
+        } else {
+            format!("{name}.vcf")
+        }
+    }
+
+    /// The details as a vCard 3.0, which address books on all platforms can import.
+    pub fn to_vcard(&self) -> String {
+        // the last word is taken as the family name, the rest as the given names
+        let (given, family) = self
+            .name
+            .trim()
+            .rsplit_once(char::is_whitespace)
+            .unwrap_or(("", self.name.trim()));
+
+        let mut lines = vec![
+            "BEGIN:VCARD".to_string(),
+            "VERSION:3.0".to_string(),
+            format!("N:{};{};;;", escape(family), escape(given.trim())),
+            format!("FN:{}", escape(&self.name)),
+        ];
+        if let Some(title) = &self.title {
+            lines.push(format!("TITLE:{}", escape(title)));
+        }
+        if let Some(organization) = &self.organization {
+            lines.push(format!("ORG:{}", escape(organization)));
+        }
+        lines.extend(
+            self.emails
+                .iter()
+                .map(|email| format!("EMAIL:{}", escape(email))),
+        );
+        lines.extend(
+            self.phones
+                .iter()
+                .map(|phone| format!("TEL:{}", escape(phone))),
+        );
+        lines.extend(self.urls.iter().map(|url| format!("URL:{}", escape(url))));
+        if let Some(address) = &self.address {
+            lines.push(format!("ADR:;;{};;;;", escape(address)));
+        }
+        if let Some(note) = &self.note {
+            lines.push(format!("NOTE:{}", escape(note)));
+        }
+        lines.push("END:VCARD".to_string());
+
+        lines.iter().map(|line| fold(line) + "\r\n").collect()
+    }
+}
+
+/// Escapes the characters with a meaning in vCard values.
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' | ',' | ';' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("\\n"),
+            '\r' => (),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Folds a line after 75 bytes, continuation lines start with a space.
+fn fold(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len());
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+        folded.push(c);
+        length += c.len_utf8();
+    }
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_values() {
+        assert_eq!(escape(r"a,b;c\d"), r"a\,b\;c\\d");
+        assert_eq!(escape("two\r\nlines\n"), r"two\nlines\n");
+    }
+
+    #[test]
+    fn folds_long_lines_at_75_bytes() {
+        let line = format!("NOTE:{}", "ä".repeat(60));
+        let folded = fold(&line);
+        let parts: Vec<&str> = folded.split("\r\n").collect();
+        assert!(parts.len() > 1);
+        assert!(parts.iter().all(|part| part.len() <= 75), "{parts:?}");
+        assert!(parts[1..].iter().all(|part| part.starts_with(' ')));
+        // multibyte characters are never split and unfolding restores the line
+        assert_eq!(folded.replace("\r\n ", ""), line);
+        assert_eq!(fold("short"), "short");
+    }
+
+    #[test]
+    fn writes_a_vcard_with_crlf_endings() {
+        let contact = Contact {
+            name: "Jane Q. Doe".to_string(),
+            title: Some("Engineer; Rust".to_string()),
+            emails: vec!["jane@example.org".to_string(), "j@example.org".to_string()],
+            address: Some("Main St 1, Berlin".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            contact.to_vcard(),
+            "BEGIN:VCARD\r\nVERSION:3.0\r\nN:Doe;Jane Q.;;;\r\nFN:Jane Q. Doe\r\n\
+             TITLE:Engineer\\; Rust\r\nEMAIL:jane@example.org\r\nEMAIL:j@example.org\r\n\
+             ADR:;;Main St 1\\, Berlin;;;;\r\nEND:VCARD\r\n"
+        );
+    }
+
+    #[test]
+    fn sanitises_the_file_name() {
+        let named = |name: &str| {
+            Contact {
+                name: name.to_string(),
+                ..Default::default()
+            }
+            .file_name()
+        };
+        assert_eq!(named("Jane  Q. Doe"), "jane-q-doe.vcf");
+        assert_eq!(named("../../etc/passwd"), "etcpasswd.vcf");
+        assert_eq!(named("Zoë Ünal"), "zoë-ünal.vcf");
+        assert_eq!(named("/ \\ :"), "contact.vcf");
+    }
+
+    #[test]
+    fn reads_lists_and_requires_a_name() {
+        let table: toml::Table = toml::from_str(
+            r#"
+name = "Jane"
+phone = ["1", "2"]
+url = "https://x"
+"#,
+        )
+        .unwrap();
+        let contact = Contact::from_table(&table).unwrap();
+        assert_eq!(contact.phones, ["1", "2"]);
+        assert_eq!(contact.urls, ["https://x"]);
+        assert!(Contact::from_table(&toml::from_str("name = \" \"").unwrap()).is_none());
+    }
+}
@@ -0,0 +1,786 @@
+use std::{collections::HashMap, sync::Arc};
+
+use egui::{
+    Color32, ColorImage, Context, ImageSource, OutputCommand, Pos2, Rect, Response, Sense,
+    SidePanel, Ui, Vec2,
+};
+use itertools::Itertools;
+
+use crate::{
+    contact::{Contact, block_contact},
+    document::{Diagnostic, DocumentPage, SourcePosition},
+    files::FileProvider,
+    overlay::Overlay,
+    popup::{Placement, Popup, set_items},
+    tags::{TagFilter, block_tags},
+    theme::Theme,
+    timeline,
+    view::View,
+};
+
+type Callback = Box<dyn FnMut(&str)>;
+
+/// The cv as a widget: the rendered page with an overlay and a popup for each labelled block,
+/// zoomable and pannable.
+///
+/// The view compiles the typst source itself, as the orientation and the resolution of the page
+/// follow the size of the view. It is created once with the builder methods, kept by the app and
+/// shown every frame with [`CvView::show`]. The document gets `sys.inputs.theme` set to `"dark"`
+/// or `"light"` following the egui theme, next to the inputs given with [`CvView::inputs`].
+pub struct CvView {
+    source: String,
+    files: Arc<dyn FileProvider>,
+    inputs: Vec<(String, String)>,
+    popup_data: Option<toml::Table>,
+    theme: Option<Theme>,
+    images: HashMap<String, ImageSource<'static>>,
+    on_block_clicked: Option<Callback>,
+    on_link_opened: Option<Callback>,
+    source_clicks: bool,
+    system_fonts: bool,
+    authoring: bool,
+
+    document: Option<DocumentPage>,
+    /// The errors of the last compilation if it failed.
+    errors: Vec<Diagnostic>,
+    areas: Vec<Overlay>,
+    texture: Option<egui::TextureHandle>,
+    canvas_size: Vec2,
+    dark_mode: bool,
+    recompile_needed: bool,
+    view: View,
+    raster_zoom: f32,
+    texture_needs_update: bool,
+    last_zoom_change: f64,
+    page_text: String,
+    pending_open: Option<String>,
+    side_panel: Option<String>,
+    tag_filter: TagFilter,
+    timeline: Vec<timeline::Entry>,
+    source_click: Option<SourcePosition>,
+    /// The blocks whose popup items were edited, with their new items.
+    popup_edits: Vec<(String, Vec<(String, String)>)>,
+    saved_contact: Option<Contact>,
+}
+
+impl CvView {
+    /// Creates the view of the typst `source`, its images, fonts and packages are read from `files`.
+    pub fn new(source: impl Into<String>, files: Arc<dyn FileProvider>) -> Self {
+        Self {
+            source: source.into(),
+            files,
+            inputs: Vec::new(),
+            popup_data: None,
+            theme: None,
+            images: HashMap::new(),
+            on_block_clicked: None,
+            on_link_opened: None,
+            source_clicks: false,
+            system_fonts: true,
+            authoring: false,
+            document: None,
+            errors: Vec::new(),
+            areas: Vec::new(),
+            texture: None,
+            canvas_size: Vec2::ZERO,
+            dark_mode: false,
+            recompile_needed: true,
+            view: View::default(),
+            raster_zoom: 1.,
+            texture_needs_update: true,
+            last_zoom_change: 0.,
+            page_text: String::new(),
+            pending_open: None,
+            side_panel: None,
+            tag_filter: TagFilter::default(),
+            timeline: Vec::new(),
+            source_click: None,
+            popup_edits: Vec::new(),
+            saved_contact: None,
+        }
+    }
+
+    /// Values passed to typst as `sys.inputs`, e.g. the language.
+    pub fn inputs(mut self, inputs: &[(&str, &str)]) -> Self {
+        self.set_inputs(inputs);
+        self
+    }
+
+    /// The popup data with the popups, tags, timeline and theme of the blocks.
+    pub fn popup_data(mut self, popup_data: toml::Table) -> Self {
+        self.set_popup_data(Some(popup_data));
+        self
+    }
+
+    /// Uses the theme instead of the one from the popup data and the `<theme>` metadata, the blocks
+    /// can still override it.
+    pub fn theme(mut self, theme: Theme) -> Self {
+        self.theme = Some(theme);
+        self
+    }
+
+    /// Registers an image that the popups show with an `image` item of the same name.
+    pub fn image(mut self, name: impl Into<String>, source: ImageSource<'static>) -> Self {
+        self.images.insert(name.into(), source);
+        self
+    }
+
+    /// Called with the label of a block when it is clicked.
+    pub fn on_block_clicked(mut self, callback: impl FnMut(&str) + 'static) -> Self {
+        self.on_block_clicked = Some(Box::new(callback));
+        self
+    }
+
+    /// Called with the url when a link in a popup is opened.
+    pub fn on_link_opened(mut self, callback: impl FnMut(&str) + 'static) -> Self {
+        self.on_link_opened = Some(Box::new(callback));
+        self
+    }
+
+    /// Resolves clicks on the page to the position in the typst sources that produced the clicked
+    /// text, shape or image, see [`CvView::take_source_click`].
+    pub fn source_clicks(mut self, enabled: bool) -> Self {
+        self.source_clicks = enabled;
+        self
+    }
+
+    /// Whether the document uses the fonts installed on the system, on by default. Without them it
+    /// renders the same on every machine.
+    pub fn system_fonts(mut self, enabled: bool) -> Self {
+        self.system_fonts = enabled;
+        self
+    }
+
+    /// Shows an editor in every popup, blocks without popup items get a popup as well.
+    ///
+    /// The edits are previewed right away and kept in the popup data of the view, see
+    /// [`CvView::take_popup_edits`].
+    pub fn set_authoring(&mut self, enabled: bool) {
+        if enabled != self.authoring {
+            self.authoring = enabled;
+            self.recompile_needed = true;
+        }
+    }
+
+    /// The blocks whose popup items were edited since the last call, with their new items.
+    pub fn take_popup_edits(&mut self) -> Vec<(String, Vec<(String, String)>)> {
+        std::mem::take(&mut self.popup_edits)
+    }
+
+    /// The contact details of the popup whose "Save contact" button was clicked since the last
+    /// call, the host saves them with [`Contact::to_vcard`].
+    pub fn take_saved_contact(&mut self) -> Option<Contact> {
+        self.saved_contact.take()
+    }
+
+    /// Changes the main source, e.g. while it is edited.
+    pub fn set_source(&mut self, source: impl Into<String>) {
+        self.source = source.into();
+        self.recompile_needed = true;
+    }
+
+    /// Changes the files of the document.
+    pub fn set_files(&mut self, files: Arc<dyn FileProvider>) {
+        self.files = files;
+        self.recompile_needed = true;
+    }
+
+    /// Changes the inputs, the document is compiled again if they changed.
+    pub fn set_inputs(&mut self, inputs: &[(&str, &str)]) {
+        let inputs: Vec<(String, String)> = inputs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        if inputs != self.inputs {
+            self.inputs = inputs;
+            self.recompile_needed = true;
+        }
+    }
+
+    /// Changes the popup data, the blocks are built again.
+    pub fn set_popup_data(&mut self, popup_data: Option<toml::Table>) {
+        self.popup_data = popup_data;
+        self.recompile_needed = true;
+    }
+
+    /// The compiled document, `None` until the view was shown or if the first compilation failed.
+    pub fn document(&self) -> Option<&DocumentPage> {
+        self.document.as_ref()
+    }
+
+    /// The errors of the last compilation, the previous document stays visible.
+    pub fn error(&self) -> Option<String> {
+        (!self.errors.is_empty()).then(|| self.errors.iter().join("\n"))
+    }
+
+    /// The errors of the last compilation if it failed, otherwise the warnings of the document.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        match &self.document {
+            Some(document) if self.errors.is_empty() => &document.warnings,
+            _ => &self.errors,
+        }
+    }
+
+    /// The font families of the document that were replaced by a fallback.
+    pub fn font_fallbacks(&self) -> &[String] {
+        self.document
+            .as_ref()
+            .map_or(&[], |document| &document.font_fallbacks)
+    }
+
+    /// The blocks with a date range, e.g. to show them with [`timeline::show`].
+    pub fn timeline(&self) -> &[timeline::Entry] {
+        &self.timeline
+    }
+
+    /// The source position of the last click on the page, if source clicks are enabled.
+    pub fn take_source_click(&mut self) -> Option<SourcePosition> {
+        self.source_click.take()
+    }
+
+    /// Opens the popup of the block with the given label, focuses the block and pans to it.
+    ///
+    /// This happens once the view is shown next, so it also works before the first compilation.
+    pub fn open_block(&mut self, label: &str) {
+        self.pending_open = Some(label.to_string());
+    }
+
+    /// Whether a block is still to be opened with the next frame.
+    pub fn is_opening_block(&self) -> bool {
+        self.pending_open.is_some()
+    }
+
+    /// The block whose popup is open, side panel first.
+    pub fn open_block_label(&self) -> Option<String> {
+        self.side_panel.clone().or_else(|| {
+            self.areas
+                .iter()
+                .find(|area| area.is_popup_visible())
+                .map(|area| area.label())
+        })
+    }
+
+    /// Closes all popups and the side panel.
+    pub fn close_popups(&mut self) {
+        self.side_panel = None;
+        for area in &mut self.areas {
+            area.hide_popup();
+        }
+    }
+
+    pub fn zoom_to_fit(&mut self) {
+        self.view = View {
+            origin: self.view.origin,
+            ..View::default()
+        };
+    }
+
+    /// Zooms to one pixel per typst point.
+    pub fn zoom_actual_size(&mut self) {
+        if let Some(document) = &self.document {
+            let center = self.view.origin + self.canvas_size / 2.;
+            let factor = 1. / (document.ratio_page_to_panel * self.view.zoom);
+            self.view.zoom_around(center, factor);
+        }
+    }
+
+    /// Shows the cv in the remaining space of the ui.
+    ///
+    /// Returns the response of the page, which takes the drag input for panning.
+    pub fn show(&mut self, ui: &mut Ui) -> Response {
+        let canvas = ui.available_rect_before_wrap();
+        let first_command = ui.ctx().output(|o| o.commands.len());
+
+        // compile the document on start, when the size of the view or the theme of egui changed
+        let dark_mode = ui.visuals().dark_mode;
+        if canvas.size() != self.canvas_size || dark_mode != self.dark_mode {
+            self.canvas_size = canvas.size();
+            self.dark_mode = dark_mode;
+            self.recompile_needed = true;
+        }
+        if self.recompile_needed {
+            self.recompile(ui.ctx());
+        }
+        self.view.origin = canvas.min;
+
+        // the background takes the drag and zoom input for the view, and the clicks for the sources
+        let sense = if self.source_clicks {
+            Sense::click_and_drag()
+        } else {
+            Sense::drag()
+        };
+        let background = ui.allocate_rect(canvas, sense);
+        let (Some(document), Some(texture)) = (&mut self.document, &mut self.texture) else {
+            if let Some(error) = self.error() {
+                ui.painter().text(
+                    canvas.center(),
+                    egui::Align2::CENTER_CENTER,
+                    error,
+                    egui::FontId::proportional(14.),
+                    ui.visuals().error_fg_color,
+                );
+            }
+            return background;
+        };
+
+        // the page is a single image, so its text is published to screen readers separately
+        let text = &self.page_text;
+        ui.ctx().accesskit_node_builder(background.id, |node| {
+            node.set_role(egui::accesskit::Role::Document);
+            node.set_label("CV");
+            node.set_description(text.as_str());
+        });
+        let upload = self.texture_needs_update;
+        render_background(ui, document, texture, &self.view, upload);
+        self.texture_needs_update = false;
+
+        if self.tag_filter.is_active() {
+            let matched = self
+                .areas
+                .iter()
+                .filter(|area| self.tag_filter.matches(area.tags()));
+            dim_unmatched(ui, texture, document, &self.view, matched);
+        }
+
+        // open a requested block once the blocks are known
+        if let Some(label) = self.pending_open.take() {
+            self.open_block_now(ui.ctx(), &label);
+        }
+
+        // check for hovering areas and start the relevant animation
+        let mut reveal = None;
+        let mut click = background
+            .clicked()
+            .then(|| background.interact_pointer_pos())
+            .flatten();
+        for area in &mut self.areas {
+            area.set_view(&self.view);
+            area.set_highlighted(
+                self.tag_filter.is_active() && self.tag_filter.matches(area.tags()),
+            );
+            let resp = ui.add(&mut *area);
+
+            // keep the block that got the keyboard focus on screen
+            if resp.gained_focus() {
+                reveal = Some(area.hover_rect());
+            }
+
+            if resp.clicked() {
+                click = click.or(resp.interact_pointer_pos());
+
+                // clicking another block while the side panel is open switches its content
+                if area.placement() == Some(Placement::SidePanel) && !area.is_popup_visible() {
+                    self.side_panel = Some(area.label());
+                }
+                if let Some(on_block_clicked) = &mut self.on_block_clicked {
+                    on_block_clicked(&area.label());
+                }
+            }
+        }
+
+        if let Some(rect) = reveal {
+            self.view.reveal(rect, canvas);
+        }
+
+        // the blocks are clicked as well, so their sources can be found too
+        if self.source_clicks
+            && let Some(pos) = click
+            && let Some(document) = &self.document
+        {
+            let page_pos = self.view.inverse_pos(pos).to_vec2() / document.ratio_page_to_panel;
+            self.source_click = document.source_at(page_pos.to_pos2());
+        }
+        self.move_focus(ui.ctx());
+
+        // the view changes after drawing, so it is shown with the next frame
+        self.handle_view_input(ui, &background);
+        self.update_raster(ui);
+        self.report_opened_links(ui.ctx(), first_command);
+        self.collect_popup_edits();
+
+        background
+    }
+
+    /// Shows the popup of a block with the side panel placement, docked to the right.
+    ///
+    /// Like other panels, it has to be added before the panel that contains the view.
+    pub fn show_side_panel(&mut self, ctx: &Context) {
+        let Some(label) = self.side_panel.clone() else {
+            return;
+        };
+        let first_command = ctx.output(|o| o.commands.len());
+
+        let mut open = true;
+        let mut pin = false;
+        SidePanel::right("popup_side_panel")
+            .resizable(true)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.heading(&label);
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui.button("✖").clicked() {
+                            open = false;
+                        }
+                        if ui.button("📌").on_hover_text("Pin as window").clicked() {
+                            pin = true;
+                        }
+                    });
+                });
+                ui.separator();
+
+                let popup = self
+                    .areas
+                    .iter_mut()
+                    .find(|area| area.label() == label)
+                    .and_then(|area| area.popup());
+                if let Some(popup) = popup {
+                    egui::ScrollArea::vertical().show(ui, |ui| popup.show_contents(ui));
+                }
+            });
+
+        if pin && let Some(area) = self.areas.iter_mut().find(|area| area.label() == label) {
+            area.pin_popup();
+        }
+        if !open || pin || ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+            self.side_panel = None;
+        }
+        self.report_opened_links(ctx, first_command);
+    }
+
+    /// Shows the tag bar to filter the blocks by their tags, the matching blocks pulse once the
+    /// selection changed.
+    pub fn show_tag_bar(&mut self, ctx: &Context) {
+        if self.tag_filter.show(ctx, &self.areas) {
+            let time = ctx.input(|i| i.time);
+            for area in &mut self.areas {
+                if self.tag_filter.is_active() && self.tag_filter.matches(area.tags()) {
+                    area.pulse(time);
+                }
+            }
+            ctx.request_repaint();
+        }
+    }
+
+    /// Keeps the edited popup items in the popup data, so they survive the next compilation, and
+    /// picks up a saved contact.
+    fn collect_popup_edits(&mut self) {
+        for area in &mut self.areas {
+            let label = area.label();
+            let Some(popup) = area.popup() else {
+                continue;
+            };
+            if let Some(contact) = popup.take_saved_contact() {
+                self.saved_contact = Some(contact);
+            }
+            if popup.take_edited() {
+                let items = popup.data().clone();
+                set_items(self.popup_data.get_or_insert_default(), &label, &items);
+                self.popup_edits.push((label, items));
+            }
+        }
+    }
+
+    /// Exports the current source and files as pdf, in the light theme as it is meant for
+    /// printing and always in the portrait layout.
+    pub fn pdf(&self) -> Result<Vec<u8>, String> {
+        let mut inputs = vec![("theme", "light")];
+        inputs.extend(
+            self.inputs
+                .iter()
+                .map(|(key, value)| (key.as_str(), value.as_str())),
+        );
+        DocumentPage::pdf(&self.source, &inputs, self.files.clone(), self.system_fonts)
+    }
+
+    fn recompile(&mut self, ctx: &Context) {
+        self.recompile_needed = false;
+
+        // the theme follows egui, the inputs of the app come after it and can override it
+        let mut inputs = vec![("theme", if self.dark_mode { "dark" } else { "light" })];
+        inputs.extend(
+            self.inputs
+                .iter()
+                .map(|(key, value)| (key.as_str(), value.as_str())),
+        );
+        let document = match DocumentPage::new(
+            &self.source,
+            self.canvas_size,
+            &inputs,
+            self.files.clone(),
+            self.system_fonts,
+        ) {
+            Ok(document) => document,
+            Err(errors) => {
+                self.errors = errors;
+                return;
+            }
+        };
+        self.errors.clear();
+
+        // clear the stored data blocks, but keep the open popups open
+        let open_popups: Vec<(String, bool)> = self
+            .areas
+            .iter_mut()
+            .filter(|area| area.is_popup_visible())
+            .map(|area| {
+                let pinned = area.popup().is_some_and(|popup| popup.is_pinned());
+                (area.label(), pinned)
+            })
+            .collect();
+        self.areas.clear();
+        self.page_text = document.text();
+
+        // the theme can be defined in the popup data and in the typst document
+        let popup_data = self.popup_data.as_ref();
+        let theme = self.theme.clone().unwrap_or_else(|| {
+            Theme::load(
+                popup_data,
+                document.metadata("theme").as_ref(),
+                self.dark_mode,
+            )
+        });
+
+        // tags can be given in the popup data and in the typst document as well
+        let tags = document.metadata("tags");
+        let contacts = document.metadata("contact");
+
+        // analyze the document
+        let no_popup_data = toml::Table::new();
+        let blocks = document.get_data_blocks();
+        for block in &blocks {
+            let mut final_rect = Rect::from_pos(Pos2::new(block.x, block.y));
+            final_rect.set_width(block.width);
+            final_rect.set_height(block.height);
+
+            let theme = theme.for_block(popup_data, &block.label);
+            let mut popup = Popup::new(
+                &block.label,
+                self.canvas_size,
+                theme.popup,
+                popup_data.unwrap_or(&no_popup_data),
+                &self.images,
+            );
+            popup.set_contact(block_contact(popup_data, contacts.as_ref(), &block.label));
+            popup.set_authoring(self.authoring);
+
+            // blocks without items only get a popup for authoring
+            let popup = (self.authoring || !popup.is_empty()).then_some(popup);
+            self.areas.push(Overlay::new(
+                final_rect,
+                block.label.clone(),
+                block.text.clone(),
+                theme.overlay,
+                popup,
+                block_tags(popup_data, tags.as_ref(), &block.label),
+            ));
+        }
+
+        self.tag_filter.retain_present(&self.areas);
+
+        let labels: Vec<String> = blocks.iter().map(|block| block.label.clone()).collect();
+        self.timeline =
+            timeline::entries(popup_data, document.metadata("timeline").as_ref(), &labels);
+
+        for (label, pinned) in open_popups {
+            if let Some(area) = self.areas.iter_mut().find(|area| area.label() == label) {
+                if pinned {
+                    area.pin_popup();
+                } else {
+                    area.show_popup();
+                }
+            }
+        }
+
+        // the new document is rendered fitted to the canvas
+        self.document = Some(document);
+        self.texture.get_or_insert_with(|| {
+            ctx.load_texture(
+                "background",
+                ColorImage::example(),
+                egui::TextureOptions::NEAREST,
+            )
+        });
+        self.raster_zoom = 1.;
+        self.texture_needs_update = true;
+    }
+
+    fn open_block_now(&mut self, ctx: &Context, label: &str) {
+        let canvas = Rect::from_min_size(self.view.origin, self.canvas_size);
+        let Some(area) = self.areas.iter_mut().find(|area| area.label() == label) else {
+            return;
+        };
+
+        if area.placement() == Some(Placement::SidePanel) {
+            self.side_panel = Some(area.label());
+        } else {
+            area.show_popup();
+        }
+        ctx.memory_mut(|mem| mem.request_focus(area.id()));
+        area.pulse(ctx.input(|i| i.time));
+        self.view.reveal(area.hover_rect(), canvas);
+    }
+
+    /// Zooms with the mouse wheel or pinch gesture around the pointer and pans by dragging the page.
+    fn handle_view_input(&mut self, ui: &Ui, background: &Response) {
+        if background.dragged() {
+            self.view.pan += background.drag_delta();
+        }
+
+        if background.contains_pointer() {
+            let (zoom_delta, scroll, pointer, time) = ui.input(|i| {
+                (
+                    i.zoom_delta(),
+                    i.smooth_scroll_delta.y,
+                    i.pointer.hover_pos(),
+                    i.time,
+                )
+            });
+            let factor = zoom_delta * (scroll / 200.).exp();
+            if factor != 1.
+                && let Some(pointer) = pointer
+            {
+                self.view.zoom_around(pointer, factor);
+                self.last_zoom_change = time;
+            }
+        }
+
+        // the keys are free for typing while a text field has the focus
+        if ui.ctx().wants_keyboard_input() {
+            return;
+        }
+        if ui.input(|i| i.key_pressed(egui::Key::Num0)) {
+            self.zoom_to_fit();
+        }
+        if ui.input(|i| i.key_pressed(egui::Key::Num1)) {
+            self.zoom_actual_size();
+        }
+    }
+
+    /// Moves the keyboard focus to the next or previous block with the arrow keys.
+    ///
+    /// The blocks are stored in the order of the document, which is their reading order. Tab and
+    /// shift+tab follow the same order, as the overlays are added in it.
+    fn move_focus(&self, ctx: &Context) {
+        let Some(index) = self
+            .areas
+            .iter()
+            .position(|area| ctx.memory(|mem| mem.has_focus(area.id())))
+        else {
+            return;
+        };
+
+        let (next, previous) = ctx.input(|i| {
+            (
+                i.key_pressed(egui::Key::ArrowDown) || i.key_pressed(egui::Key::ArrowRight),
+                i.key_pressed(egui::Key::ArrowUp) || i.key_pressed(egui::Key::ArrowLeft),
+            )
+        });
+        let target = if next {
+            index + 1
+        } else if previous && index > 0 {
+            index - 1
+        } else {
+            return;
+        };
+
+        if let Some(area) = self.areas.get(target) {
+            ctx.memory_mut(|mem| mem.request_focus(area.id()));
+        }
+    }
+
+    /// Renders the page again at the current zoom level once the zooming stopped, so it stays crisp.
+    fn update_raster(&mut self, ui: &Ui) {
+        let Some(document) = &mut self.document else {
+            return;
+        };
+        let (time, max_texture_side) = ui.input(|i| (i.time, i.max_texture_side));
+
+        // keep the image within the texture limits, larger zoom levels are scaled up
+        let fitted_size = document.fitted_size();
+        let max_zoom = max_texture_side as f32 / fitted_size.max_elem();
+        let zoom = self.view.zoom.min(max_zoom);
+
+        if (zoom - self.raster_zoom).abs() > 1e-3 {
+            let settle_time = 0.2;
+            if time - self.last_zoom_change > settle_time {
+                document.rasterize(zoom);
+                self.raster_zoom = zoom;
+                self.texture_needs_update = true;
+            } else {
+                ui.ctx()
+                    .request_repaint_after(std::time::Duration::from_secs_f64(settle_time));
+            }
+        }
+    }
+
+    /// Passes the links opened since the given output command to the callback.
+    fn report_opened_links(&mut self, ctx: &Context, first_command: usize) {
+        let Some(on_link_opened) = &mut self.on_link_opened else {
+            return;
+        };
+
+        let urls: Vec<String> = ctx.output(|o| {
+            o.commands
+                .iter()
+                .skip(first_command)
+                .filter_map(|command| match command {
+                    OutputCommand::OpenUrl(open_url) => Some(open_url.url.clone()),
+                    _ => None,
+                })
+                .collect()
+        });
+        for url in urls {
+            on_link_opened(&url);
+        }
+    }
+}
+
+/// Dims the page, except for the given blocks which are drawn again on top of the dimmed page.
+fn dim_unmatched<'a>(
+    ui: &mut Ui,
+    texture_handle: &egui::TextureHandle,
+    document: &DocumentPage,
+    view: &View,
+    matched: impl Iterator<Item = &'a Overlay>,
+) {
+    let page = view.transform_rect(Rect::from_min_size(Pos2::ZERO, document.fitted_size()));
+    let fill = ui.visuals().panel_fill;
+    ui.painter().rect_filled(page, 0., fill.gamma_multiply(0.8));
+
+    for area in matched {
+        let rect = area.hover_rect().intersect(page);
+        let uv = Rect::from_min_max(
+            ((rect.min - page.min) / page.size()).to_pos2(),
+            ((rect.max - page.min) / page.size()).to_pos2(),
+        );
+        ui.painter().rect_filled(rect, 0., fill);
+        ui.painter()
+            .image(texture_handle.id(), rect, uv, Color32::WHITE);
+    }
+}
+
+fn render_background(
+    ui: &mut Ui,
+    document: &DocumentPage,
+    texture_handle: &mut egui::TextureHandle,
+    view: &View,
+    upload: bool,
+) {
+    // set the background image derived from the typst document, only if it was rendered again
+    if upload {
+        let final_img = ColorImage::from_rgba_unmultiplied(
+            [
+                document.image.width as usize,
+                document.image.height as usize,
+            ],
+            document.as_vec(),
+        );
+        texture_handle.set(final_img, egui::TextureOptions::LINEAR);
+    }
+
+    // the image is scaled to the zoom level until it is rendered at that level
+    let rect = view.transform_rect(Rect::from_min_size(Pos2::ZERO, document.fitted_size()));
+    let uv = Rect::from_min_max(Pos2::ZERO, Pos2::new(1., 1.));
+    ui.painter()
+        .image(texture_handle.id(), rect, uv, Color32::WHITE);
+}
@@ -0,0 +1,69 @@
+/// Parses the block label from a url fragment like `#block=projects`.
+///
+/// The browser percent-encodes labels with spaces or non-ascii characters, they are decoded
+/// again; a malformed encoding is kept as it is.
+#[cfg(target_arch = "wasm32")]
+pub fn parse_fragment(fragment: &str) -> Option<String> {
+    fragment
+        .trim_start_matches('#')
+        .split('&')
+        .find_map(|part| part.strip_prefix("block="))
+        .filter(|label| !label.is_empty())
+        .map(|label| {
+            js_sys::decode_uri_component(label)
+                .map(String::from)
+                .unwrap_or_else(|_| label.to_string())
+        })
+}
+
+/// Returns the label given with `--open <label>` or `--open=<label>` on the command line.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn open_argument() -> Option<String> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--open" {
+            return args.next();
+        }
+        if let Some(label) = arg.strip_prefix("--open=") {
+            return Some(label.to_string());
+        }
+    }
+    None
+}
+
+/// Reads the current fragment from the browser, it also changes with the back button.
+#[cfg(target_arch = "wasm32")]
+pub fn current_fragment() -> Option<String> {
+    let hash = web_sys::window()?.location().hash().ok()?;
+    parse_fragment(&hash)
+}
+
+/// Writes the open block to the fragment.
+///
+/// Opening a block adds a history entry, so the back button closes the popup again. Closing it
+/// replaces the entry instead, to keep the history free of closed popups.
+#[cfg(target_arch = "wasm32")]
+pub fn set_fragment(label: Option<&str>) {
+    let Some(window) = web_sys::window() else {
+        return;
+    };
+
+    match label {
+        Some(label) => {
+            window.location().set_hash(&format!("block={label}")).ok();
+        }
+        None => {
+            let location = window.location();
+            let url = format!(
+                "{}{}",
+                location.pathname().unwrap_or_default(),
+                location.search().unwrap_or_default()
+            );
+            if let Ok(history) = window.history() {
+                history
+                    .replace_state_with_url(&eframe::wasm_bindgen::JsValue::NULL, "", Some(&url))
+                    .ok();
+            }
+        }
+    }
+}
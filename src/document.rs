@@ -1,37 +1,50 @@
 use std::{
     collections::HashMap,
+    path::{Path, PathBuf},
     sync::{Arc, Mutex},
 };
 
-use egui::Vec2;
+use egui::{Pos2, Vec2};
 use itertools::Itertools;
 use typst::{
     Library, World,
-    diag::{FileError, FileResult},
-    foundations::{Bytes, Datetime},
-    layout::{FrameItem, Page, PagedDocument},
-    syntax::{FileId, Source},
+    diag::{FileError, FileResult, PackageError, Severity, SourceDiagnostic},
+    foundations::{Bytes, Datetime, Dict, IntoValue, Label, Str, Value},
+    layout::{Abs, FrameItem, Page, PagedDocument, Point, Size},
+    syntax::{FileId, Source, Span, SyntaxKind, package::PackageSpec},
     text::{Font, FontBook},
     utils::LazyHash,
+    visualize::{Curve, CurveItem},
 };
 use typst_kit::fonts::{FontSearcher, FontSlot};
 
+use crate::files::FileProvider;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::files::Filesystem;
+
 pub struct TypstWorld {
     source: Source,
     library: LazyHash<Library>,
     book: LazyHash<FontBook>,
-    #[cfg(not(target_arch = "wasm32"))]
-    root: std::path::PathBuf,
-    files: Arc<Mutex<HashMap<FileId, FileEntry>>>,
+    files: Arc<dyn FileProvider>,
+    /// A package directory outside of the files, from `TYPST_PACKAGE_PATH`.
+    packages: Option<Box<dyn FileProvider>>,
+    cache: Arc<Mutex<HashMap<FileId, FileEntry>>>,
     fonts: Vec<FontSlot>,
-    #[cfg(target_arch = "wasm32")]
-    content_dir: include_dir::Dir<'static>,
+    /// Fonts from the `fonts` directory of the files, before the slots in the font book.
+    bundled_fonts: Vec<Font>,
 }
 
 pub struct DocumentPage {
+    pub document: PagedDocument,
     pub page: Page,
     pub image: Image,
     pub ratio_page_to_panel: f32,
+    /// The font families that are not available and were replaced by a fallback.
+    pub font_fallbacks: Vec<String>,
+    pub warnings: Vec<Diagnostic>,
+    /// Kept to resolve the spans of the page to their sources.
+    world: TypstWorld,
 }
 
 pub struct Image {
@@ -40,64 +53,137 @@ pub struct Image {
     pub height: u32,
 }
 
+#[derive(serde::Serialize)]
 pub struct DataBlock {
     pub x: f32,
     pub y: f32,
     pub width: f32,
     pub height: f32,
     pub label: String,
+    /// The text of the block, one line per text line on the page.
+    pub text: String,
 }
 
-impl DocumentPage {
-    #[cfg(not(target_arch = "wasm32"))]
-    pub fn new(input: &str, panel_size: Vec2) -> Result<Self, String> {
-        let world = TypstWorld::new(input.to_string());
-        DocumentPage::new_followup(world, panel_size)
+/// A position in the sources of the document.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SourcePosition {
+    /// The file relative to the files of the document, `None` for the main source.
+    pub path: Option<PathBuf>,
+    /// The line, starting at 0.
+    pub line: usize,
+    /// The column in characters, starting at 0.
+    pub column: usize,
+}
+
+/// An error or warning of typst, with its position if it comes from the sources of the document.
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub message: String,
+    pub is_error: bool,
+    pub position: Option<SourcePosition>,
+}
+
+impl std::fmt::Display for SourcePosition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let path = self.path.as_deref().unwrap_or(Path::new("main"));
+        write!(
+            f,
+            "{}:{}:{}",
+            path.display(),
+            self.line + 1,
+            self.column + 1
+        )
     }
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.position {
+            Some(position) => write!(f, "{position}: {}", self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
 
-    #[cfg(target_arch = "wasm32")]
+impl DocumentPage {
+    /// Compiles the document, `inputs` are available in typst as `sys.inputs`.
+    ///
+    /// Without `system_fonts` the document only uses the fonts in the `fonts` directory of the
+    /// files and the fonts embedded in typst, so it renders the same on every machine. The
+    /// browser never has system fonts.
     pub fn new(
         input: &str,
         panel_size: Vec2,
-        content_dir: include_dir::Dir<'static>,
-    ) -> Result<Self, String> {
-        let world = TypstWorld::new(input.to_string(), content_dir);
+        inputs: &[(&str, &str)],
+        files: Arc<dyn FileProvider>,
+        system_fonts: bool,
+    ) -> Result<Self, Vec<Diagnostic>> {
+        let world = TypstWorld::new(input.to_string(), inputs, files, system_fonts);
         DocumentPage::new_followup(world, panel_size)
     }
 
-    pub fn new_followup(mut world: TypstWorld, panel_size: Vec2) -> Result<Self, String> {
-        let world_source = &mut world.source;
+    /// Exports the document as pdf, always in the portrait layout independent of the window shape.
+    pub fn pdf(
+        input: &str,
+        inputs: &[(&str, &str)],
+        files: Arc<dyn FileProvider>,
+        system_fonts: bool,
+    ) -> Result<Vec<u8>, String> {
+        let world = TypstWorld::new(input.to_string(), inputs, files, system_fonts);
+        DocumentPage::pdf_followup(&world)
+    }
 
-        // flip to horizontal if width is bigger than height
-        if panel_size.x > panel_size.y {
-            if let Some((line_idx, line_text)) = world_source
-                .text()
-                .lines()
-                .find_position(|t| t.contains("flipped"))
-            {
-                let replacement = line_text.replace("false", "true");
-                world_source.edit(
-                    world_source.line_to_range(line_idx).unwrap(),
-                    replacement.as_str(),
-                );
-            }
+    /// Compiles all pages of the document in the given orientation, independent of a panel.
+    ///
+    /// Also returns the font families that were replaced by a fallback.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn compile(
+        input: &str,
+        inputs: &[(&str, &str)],
+        files: Arc<dyn FileProvider>,
+        system_fonts: bool,
+        landscape: bool,
+    ) -> Result<(PagedDocument, Vec<String>), String> {
+        let mut world = TypstWorld::new(input.to_string(), inputs, files, system_fonts);
+        apply_orientation(&mut world.source, landscape);
+        let compiled = typst::compile(&world);
+        let document = compiled
+            .output
+            .map_err(|errors| format_diagnostics(&errors))?;
+        Ok((document, font_fallbacks(&compiled.warnings)))
+    }
 
-            // change the style_portrait.typ file to style_landscape.typ if they exist
-            if let Some((line_idx, line_text)) = world_source
-                .text()
-                .lines()
-                .find_position(|t| t.contains("style_portrait.typ"))
-            {
-                let replacement = line_text.replace("portrait", "landscape");
-                world_source.edit(world_source.line_to_range(line_idx).unwrap(), &replacement);
-            }
-        }
+    /// Exports an already compiled document as pdf.
+    pub fn export_pdf(document: &PagedDocument) -> Result<Vec<u8>, String> {
+        typst_pdf::pdf(document, &typst_pdf::PdfOptions::default())
+            .map_err(|errors| format_diagnostics(&errors))
+    }
+
+    fn pdf_followup(world: &TypstWorld) -> Result<Vec<u8>, String> {
+        let document: PagedDocument = typst::compile(world)
+            .output
+            .map_err(|errors| format_diagnostics(&errors))?;
+        DocumentPage::export_pdf(&document)
+    }
+
+    pub fn new_followup(mut world: TypstWorld, panel_size: Vec2) -> Result<Self, Vec<Diagnostic>> {
+        // flip to horizontal if width is bigger than height
+        apply_orientation(&mut world.source, panel_size.x > panel_size.y);
 
         // compile the document
-        let document: PagedDocument = typst::compile(&world).output.expect("Typst compile error.");
+        let compiled = typst::compile(&world);
+        let document: PagedDocument = compiled
+            .output
+            .map_err(|errors| world.diagnostics(&errors))?;
+        let font_fallbacks = font_fallbacks(&compiled.warnings);
+        let warnings = world.diagnostics(&compiled.warnings);
 
         if document.pages.is_empty() {
-            return Err("No pages found".to_string());
+            return Err(vec![Diagnostic {
+                message: "No pages found".to_string(),
+                is_error: true,
+                position: None,
+            }]);
         }
 
         // for now only take the first page
@@ -127,25 +213,57 @@ impl DocumentPage {
         };
 
         Ok(Self {
+            document,
             page,
             image,
             ratio_page_to_panel,
+            font_fallbacks,
+            warnings,
+            world,
         })
     }
 
+    /// Returns the value of a `#metadata(..) <label>` element of the document, converted to toml.
+    pub fn metadata(&self, label: &str) -> Option<toml::Value> {
+        let label = Label::new(typst::utils::PicoStr::intern(label));
+        let content = self.document.introspector.query_label(label).ok()?;
+        value_to_toml(&content.get_by_name("value").ok()?)
+    }
+
+    /// The typst files besides the main source that the compilation read, e.g. the imported ones.
+    pub fn source_files(&self) -> Vec<PathBuf> {
+        self.world.source_files()
+    }
+
+    /// Size of the page fitted to the panel, the size of the image at zoom level 1.
+    pub fn fitted_size(&self) -> Vec2 {
+        let size = self.page.frame.size();
+        Vec2::new(size.x.to_pt() as f32, size.y.to_pt() as f32) * self.ratio_page_to_panel
+    }
+
+    /// Renders the page again at `zoom` times the size fitted to the panel.
+    pub fn rasterize(&mut self, zoom: f32) {
+        let pixmap = typst_render::render(&self.page, self.ratio_page_to_panel * zoom);
+        self.image = Image {
+            width: pixmap.width(),
+            height: pixmap.height(),
+            data: pixmap.take(),
+        };
+    }
+
     pub fn as_vec(&self) -> &Vec<u8> {
         &self.image.data
     }
 
     fn filter_for_relevant_blocks(
-        &self,
         frame: &typst::layout::Frame,
         mut blocks: Vec<DataBlock>,
         offset: typst::layout::Point,
+        scale: f32,
+        outset: f32,
     ) -> Vec<DataBlock> {
         let mut grid_found = false;
         let mut label_takeover = String::new();
-        let outset = 8.;
 
         for (pos, item) in frame.items() {
             match item {
@@ -155,15 +273,13 @@ impl DocumentPage {
                         let pos = typst::layout::Point::new(pos.x + offset.x, pos.y + offset.y);
 
                         let block = DataBlock {
-                            x: self.ratio_page_to_panel * pos.x.to_pt() as f32 - outset,
-                            y: self.ratio_page_to_panel * pos.y.to_pt() as f32 - outset,
-                            width: group_item.frame.width().to_pt() as f32
-                                * self.ratio_page_to_panel
-                                + (2. * outset),
-                            height: group_item.frame.height().to_pt() as f32
-                                * self.ratio_page_to_panel
+                            x: scale * pos.x.to_pt() as f32 - outset,
+                            y: scale * pos.y.to_pt() as f32 - outset,
+                            width: group_item.frame.width().to_pt() as f32 * scale + (2. * outset),
+                            height: group_item.frame.height().to_pt() as f32 * scale
                                 + (2. * outset),
                             label: label_takeover.clone(),
+                            text: frame_text(&group_item.frame),
                         };
 
                         blocks.push(block);
@@ -173,15 +289,21 @@ impl DocumentPage {
                             x: offset.x + pos.x,
                             y: offset.y + pos.y,
                         };
-                        blocks = self.filter_for_relevant_blocks(&group_item.frame, blocks, offset);
+                        blocks = Self::filter_for_relevant_blocks(
+                            &group_item.frame,
+                            blocks,
+                            offset,
+                            scale,
+                            outset,
+                        );
                     }
                 }
-                FrameItem::Tag(typst::introspection::Tag::Start(content)) => {
-                    if content.elem().name() == "grid" {
-                        grid_found = true;
-                        let label = content.label().unwrap().resolve();
-                        label_takeover = String::from(label.as_str());
-                    }
+                FrameItem::Tag(typst::introspection::Tag::Start(content))
+                    if content.elem().name() == "grid" =>
+                {
+                    grid_found = true;
+                    let label = content.label().unwrap().resolve();
+                    label_takeover = String::from(label.as_str());
                 }
                 _ => (),
             }
@@ -190,13 +312,207 @@ impl DocumentPage {
         blocks
     }
 
+    /// The position in the sources of the text, shape or image at a point of the page, in points.
+    pub fn source_at(&self, point: Pos2) -> Option<SourcePosition> {
+        let point = Point::new(Abs::pt(point.x as f64), Abs::pt(point.y as f64));
+        let (span, offset) = span_at(&self.page.frame, point)?;
+        self.world.position(span, offset as usize)
+    }
+
+    /// The text of the whole page in reading order.
+    pub fn text(&self) -> String {
+        frame_text(&self.page.frame)
+    }
+
     pub fn get_data_blocks(&self) -> Vec<DataBlock> {
         let mut blocks = Vec::new();
         let offset = typst::layout::Point::zero();
-        blocks = self.filter_for_relevant_blocks(&self.page.frame, blocks, offset);
+        let outset = 8.;
+        blocks = Self::filter_for_relevant_blocks(
+            &self.page.frame,
+            blocks,
+            offset,
+            self.ratio_page_to_panel,
+            outset,
+        );
 
         blocks
     }
+
+    /// The blocks of any page in page coordinates, in points and without an outset.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn page_blocks(page: &Page) -> Vec<DataBlock> {
+        let offset = typst::layout::Point::zero();
+        Self::filter_for_relevant_blocks(&page.frame, Vec::new(), offset, 1., 0.)
+    }
+}
+
+/// Switches the source to the landscape layout, by setting the `flipped` line to true and
+/// replacing `style_portrait.typ` with `style_landscape.typ` if they exist.
+fn apply_orientation(world_source: &mut Source, landscape: bool) {
+    if !landscape {
+        return;
+    }
+
+    if let Some((line_idx, line_text)) = world_source
+        .text()
+        .lines()
+        .find_position(|t| t.contains("flipped"))
+    {
+        let replacement = line_text.replace("false", "true");
+        world_source.edit(
+            world_source.line_to_range(line_idx).unwrap(),
+            replacement.as_str(),
+        );
+    }
+
+    // change the style_portrait.typ file to style_landscape.typ if they exist
+    if let Some((line_idx, line_text)) = world_source
+        .text()
+        .lines()
+        .find_position(|t| t.contains("style_portrait.typ"))
+    {
+        let replacement = line_text.replace("portrait", "landscape");
+        world_source.edit(world_source.line_to_range(line_idx).unwrap(), &replacement);
+    }
+}
+
+fn format_diagnostics(diagnostics: &[SourceDiagnostic]) -> String {
+    diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.message.as_str())
+        .join("\n")
+}
+
+/// Picks the font families that typst replaced by a fallback from the warnings.
+fn font_fallbacks(warnings: &[SourceDiagnostic]) -> Vec<String> {
+    warnings
+        .iter()
+        .filter_map(|warning| warning.message.strip_prefix("unknown font family: "))
+        .map(String::from)
+        .unique()
+        .collect()
+}
+
+/// Finds the span of the topmost glyph, shape or image at a point of the frame.
+///
+/// Glyphs carry the offset of their text in the span as well.
+fn span_at(frame: &typst::layout::Frame, point: Point) -> Option<(Span, u16)> {
+    for (pos, item) in frame.items().rev() {
+        let local = point - *pos;
+        let found = match item {
+            FrameItem::Group(group_item) => {
+                // the content of rotated, scaled or moved groups is found in their own coordinates
+                let local = group_item
+                    .transform
+                    .invert()
+                    .map(|inverse| local.transform(inverse));
+                local
+                    .filter(|local| {
+                        group_item
+                            .clip
+                            .as_ref()
+                            .is_none_or(|clip| clip_contains(clip, *local))
+                    })
+                    .and_then(|local| span_at(&group_item.frame, local))
+            }
+            FrameItem::Text(text_item) => {
+                // the text is positioned at its baseline
+                let metrics = text_item.font.metrics();
+                let top = -metrics.ascender.at(text_item.size);
+                let bottom = -metrics.descender.at(text_item.size);
+                let mut x = Abs::zero();
+                text_item
+                    .glyphs
+                    .iter()
+                    .find(|glyph| {
+                        let start = x;
+                        x += glyph.x_advance.at(text_item.size);
+                        (top..bottom).contains(&local.y) && (start..x).contains(&local.x)
+                    })
+                    .map(|glyph| glyph.span)
+            }
+            FrameItem::Shape(shape, span) => {
+                contains(shape.geometry.bbox_size(), local).then_some((*span, 0))
+            }
+            FrameItem::Image(_, size, span) => contains(*size, local).then_some((*span, 0)),
+            _ => None,
+        };
+
+        if let Some((span, offset)) = found
+            && !span.is_detached()
+        {
+            return Some((span, offset));
+        }
+    }
+
+    None
+}
+
+fn contains(size: Size, point: Point) -> bool {
+    (Abs::zero()..size.x).contains(&point.x) && (Abs::zero()..size.y).contains(&point.y)
+}
+
+/// Whether the point is within the bounds of the points of a clip curve, hidden content outside
+/// of it can't be clicked.
+fn clip_contains(clip: &Curve, point: Point) -> bool {
+    let points = clip.0.iter().flat_map(|item| match item {
+        CurveItem::Move(p) | CurveItem::Line(p) => vec![*p],
+        CurveItem::Cubic(p1, p2, p3) => vec![*p1, *p2, *p3],
+        CurveItem::Close => vec![],
+    });
+    let (min, max) = points.fold(
+        (Point::splat(Abs::inf()), Point::splat(-Abs::inf())),
+        |(min, max), p| (min.min(p), max.max(p)),
+    );
+    (min.x..=max.x).contains(&point.x) && (min.y..=max.y).contains(&point.y)
+}
+
+/// Collects the text of a frame in the order of the document, starting a new line whenever the
+/// vertical position of the text changes.
+fn frame_text(frame: &typst::layout::Frame) -> String {
+    fn collect(
+        frame: &typst::layout::Frame,
+        offset: f64,
+        last_y: &mut Option<f64>,
+        text: &mut String,
+    ) {
+        for (pos, item) in frame.items() {
+            let y = offset + pos.y.to_pt();
+            match item {
+                FrameItem::Group(group_item) => collect(&group_item.frame, y, last_y, text),
+                FrameItem::Text(text_item) => {
+                    if last_y.is_some_and(|last_y| (last_y - y).abs() > 0.5) {
+                        text.push('\n');
+                    }
+                    text.push_str(&text_item.text);
+                    *last_y = Some(y);
+                }
+                _ => (),
+            }
+        }
+    }
+
+    let mut text = String::new();
+    collect(frame, 0., &mut None, &mut text);
+    text
+}
+
+fn value_to_toml(value: &Value) -> Option<toml::Value> {
+    Some(match value {
+        Value::Bool(v) => toml::Value::Boolean(*v),
+        Value::Int(v) => toml::Value::Integer(*v),
+        Value::Float(v) => toml::Value::Float(*v),
+        Value::Str(v) => toml::Value::String(v.to_string()),
+        Value::Color(v) => toml::Value::String(v.to_hex().to_string()),
+        Value::Array(v) => toml::Value::Array(v.iter().filter_map(value_to_toml).collect()),
+        Value::Dict(v) => toml::Value::Table(
+            v.iter()
+                .filter_map(|(key, v)| Some((key.to_string(), value_to_toml(v)?)))
+                .collect(),
+        ),
+        _ => return None,
+    })
 }
 
 #[derive(Clone, Debug)]
@@ -206,11 +522,8 @@ struct FileEntry {
 }
 
 impl FileEntry {
-    fn new(bytes: Vec<u8>, source: Option<Source>) -> Self {
-        Self {
-            bytes: Bytes::new(bytes),
-            source,
-        }
+    fn new(bytes: Bytes, source: Option<Source>) -> Self {
+        Self { bytes, source }
     }
 
     fn source(&mut self, id: FileId) -> FileResult<Source> {
@@ -226,71 +539,141 @@ impl FileEntry {
     }
 }
 
+/// The directory of a package relative to the package directory, e.g. `preview/cetz/0.3.4`.
+fn package_dir(spec: &PackageSpec) -> String {
+    format!("{}/{}/{}", spec.namespace, spec.name, spec.version)
+}
+
+fn library(inputs: &[(&str, &str)]) -> Library {
+    let inputs: Dict = inputs
+        .iter()
+        .map(|(key, value)| (Str::from(*key), value.into_value()))
+        .collect();
+    Library::builder().with_inputs(inputs).build()
+}
+
 impl TypstWorld {
-    #[cfg(not(target_arch = "wasm32"))]
-    fn new(source: String) -> Self {
-        let fonts = FontSearcher::new().include_system_fonts(true).search();
-        Self {
-            source: Source::detached(source),
-            library: LazyHash::new(Library::default()),
-            book: LazyHash::new(fonts.book),
-            root: std::path::PathBuf::from("./assets/"),
-            files: Arc::new(Mutex::new(HashMap::new())),
-            fonts: fonts.fonts,
+    fn new(
+        source: String,
+        inputs: &[(&str, &str)],
+        files: Arc<dyn FileProvider>,
+        system_fonts: bool,
+    ) -> Self {
+        // there are no system fonts in the browser
+        let system_fonts = system_fonts && cfg!(not(target_arch = "wasm32"));
+
+        // the project fonts take precedence over the system fonts and the fonts embedded in typst
+        let fonts = FontSearcher::new()
+            .include_system_fonts(system_fonts)
+            .search();
+        let bundled_fonts: Vec<Font> = files
+            .list(Path::new("fonts"))
+            .iter()
+            .filter_map(|path| files.read(path).ok())
+            .flat_map(Font::iter)
+            .collect();
+        let mut book = FontBook::new();
+        for font in &bundled_fonts {
+            book.push(font.info().clone());
+        }
+        for index in 0..fonts.fonts.len() {
+            book.push(fonts.book.info(index).expect("font in book").clone());
         }
-    }
 
-    #[cfg(target_arch = "wasm32")]
-    fn new(source: String, content_dir: include_dir::Dir<'static>) -> Self {
-        let fonts = FontSearcher::new().include_system_fonts(true).search();
+        #[cfg(not(target_arch = "wasm32"))]
+        let packages = std::env::var_os("TYPST_PACKAGE_PATH")
+            .map(|path| Box::new(Filesystem::new(path)) as Box<dyn FileProvider>);
+        #[cfg(target_arch = "wasm32")]
+        let packages = None;
+
         Self {
             source: Source::detached(source),
-            library: LazyHash::new(Library::default()),
-            book: LazyHash::new(fonts.book),
-            files: Arc::new(Mutex::new(HashMap::new())),
+            library: LazyHash::new(library(inputs)),
+            book: LazyHash::new(book),
+            files,
+            packages,
+            cache: Arc::new(Mutex::new(HashMap::new())),
             fonts: fonts.fonts,
-            content_dir,
+            bundled_fonts,
         }
     }
 
-    #[cfg(not(target_arch = "wasm32"))]
+    /// The typst files read from the files of the document, packages are left out.
+    fn source_files(&self) -> Vec<PathBuf> {
+        let Ok(cache) = self.cache.lock() else {
+            return Vec::new();
+        };
+        cache
+            .keys()
+            .filter(|id| id.package().is_none())
+            .map(|id| id.vpath().as_rootless_path().to_path_buf())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "typ"))
+            .sorted()
+            .collect()
+    }
+
     fn file(&self, id: FileId) -> FileResult<FileEntry> {
-        let mut files = self.files.lock().map_err(|_| FileError::AccessDenied)?;
-        if let Some(entry) = files.get(&id) {
+        let mut cache = self.cache.lock().map_err(|_| FileError::AccessDenied)?;
+        if let Some(entry) = cache.get(&id) {
             return Ok(entry.clone());
         }
 
-        let path = id
-            .vpath()
-            .resolve(&self.root)
-            .ok_or(FileError::AccessDenied)?;
-
-        let content = std::fs::read(&path).map_err(|error| FileError::from_io(error, &path))?;
+        let path = id.vpath().as_rootless_path();
+        let bytes = match id.package() {
+            Some(spec) => {
+                let (files, dir) = self.package(spec)?;
+                files.read(&dir.join(path))?
+            }
+            None => self.files.read(path)?,
+        };
 
-        Ok(files
+        Ok(cache
             .entry(id)
-            .or_insert(FileEntry::new(content, None))
+            .or_insert(FileEntry::new(bytes, None))
             .clone())
     }
 
-    #[cfg(target_arch = "wasm32")]
-    fn file(&self, id: FileId) -> FileResult<FileEntry> {
-        let mut files = self.files.lock().map_err(|_| FileError::AccessDenied)?;
-        if let Some(entry) = files.get(&id) {
-            return Ok(entry.clone());
+    /// Resolves a span and a byte offset into it to its file, line and column, `None` for spans in
+    /// packages.
+    fn position(&self, span: Span, offset: usize) -> Option<SourcePosition> {
+        let id = span.id()?;
+        if id.package().is_some() {
+            return None;
         }
 
-        let path = id
-            .vpath()
-            .as_rootless_path()
-            .to_str()
-            .ok_or(FileError::AccessDenied)?;
+        // the offset only points into markup text, generated text starts at its call
+        let source = World::source(self, id).ok()?;
+        let node = source.find(span)?;
+        let byte = match node.kind() {
+            SyntaxKind::Text => node.offset() + offset.min(node.len()),
+            _ => node.offset(),
+        };
+        Some(SourcePosition {
+            path: (id != self.main()).then(|| id.vpath().as_rootless_path().to_path_buf()),
+            line: source.byte_to_line(byte)?,
+            column: source.byte_to_column(byte)?,
+        })
+    }
 
-        let content = self.content_dir.get_file(path).unwrap();
-        Ok(files
-            .entry(id)
-            .or_insert(FileEntry::new(content.contents().to_vec(), None))
-            .clone())
+    fn diagnostics(&self, diagnostics: &[SourceDiagnostic]) -> Vec<Diagnostic> {
+        diagnostics
+            .iter()
+            .map(|diagnostic| Diagnostic {
+                message: diagnostic.message.to_string(),
+                is_error: diagnostic.severity == Severity::Error,
+                position: self.position(diagnostic.span, 0),
+            })
+            .collect()
+    }
+
+    /// Finds a package in the `packages` directory of the files or in `TYPST_PACKAGE_PATH`, both
+    /// in the layout of typst's package directory, so no package is downloaded.
+    fn package(&self, spec: &PackageSpec) -> FileResult<(&dyn FileProvider, PathBuf)> {
+        let dir = PathBuf::from(package_dir(spec));
+        std::iter::once((self.files.as_ref(), Path::new("packages").join(&dir)))
+            .chain(self.packages.as_deref().map(|packages| (packages, dir)))
+            .find(|(files, dir)| files.read(&dir.join("typst.toml")).is_ok())
+            .ok_or_else(|| FileError::Package(PackageError::NotFound(spec.clone())))
     }
 }
 
@@ -328,7 +711,10 @@ impl World for TypstWorld {
 
     #[doc = " Try to access the font with the given index in the font book."]
     fn font(&self, index: usize) -> Option<Font> {
-        self.fonts[index].get()
+        match index.checked_sub(self.bundled_fonts.len()) {
+            Some(index) => self.fonts.get(index)?.get(),
+            None => self.bundled_fonts.get(index).cloned(),
+        }
     }
 
     #[doc = " Get the current date."]
@@ -338,7 +724,142 @@ impl World for TypstWorld {
     #[doc = ""]
     #[doc = " If this function returns `None`, Typst\'s `datetime` function will"]
     #[doc = " return an error."]
-    fn today(&self, _offset: Option<i64>) -> Option<Datetime> {
-        todo!()
+    fn today(&self, offset: Option<i64>) -> Option<Datetime> {
+        let (seconds, local_offset) = clock();
+        let offset = offset.map_or(local_offset, |hours| hours * 3600);
+        let (year, month, day) = civil_date((seconds + offset).div_euclid(86400));
+        Datetime::from_ymd(year, month, day)
+    }
+}
+
+/// The seconds since the unix epoch and the offset of the local time zone in seconds.
+///
+/// Native builds have no time zone database, so the local date is the UTC date there.
+#[cfg(not(target_arch = "wasm32"))]
+fn clock() -> (i64, i64) {
+    let seconds = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    (seconds as i64, 0)
+}
+
+/// The seconds since the unix epoch and the offset of the local time zone in seconds.
+#[cfg(target_arch = "wasm32")]
+fn clock() -> (i64, i64) {
+    let now = js_sys::Date::new_0();
+    // the browser gives the offset in minutes behind UTC
+    let offset = -now.get_timezone_offset() * 60.;
+    ((now.get_time() / 1000.) as i64, offset as i64)
+}
+
+/// The year, month and day of a day counted from 1970-01-01 in the proleptic Gregorian calendar.
+fn civil_date(days: i64) -> (i32, u8, u8) {
+    // shifted to start in March, so the leap day is the last day of the year
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year as i32, month as u8, day as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::files::Memory;
+
+    // the fallbacks are read from the wording of the typst warning, this pins it
+    #[test]
+    #[cfg(not(target_arch = "wasm32"))]
+    fn reports_unknown_font_families() {
+        let source = "#set text(font: (\"No Such Font\", \"Libertinus Serif\"))\nHello";
+        let (_, fallbacks) =
+            DocumentPage::compile(source, &[], Arc::new(Memory::default()), false, false).unwrap();
+        assert_eq!(fallbacks, ["no such font"]);
+    }
+
+    #[test]
+    fn counts_civil_dates_from_the_epoch() {
+        assert_eq!(civil_date(0), (1970, 1, 1));
+        assert_eq!(civil_date(-1), (1969, 12, 31));
+        assert_eq!(civil_date(11_016), (2000, 2, 29));
+        assert_eq!(civil_date(19_723), (2024, 1, 1));
+    }
+
+    #[test]
+    fn shows_the_date_of_today() {
+        let source = "#datetime.today().display() #datetime.today(offset: -12).display()";
+        let page = DocumentPage::new(
+            source,
+            Vec2::new(100., 100.),
+            &[],
+            Arc::new(Memory::default()),
+            false,
+        )
+        .unwrap();
+        let (seconds, _) = clock();
+        let (year, _, _) = civil_date(seconds / 86400);
+        assert!(page.text().contains(&year.to_string()), "{}", page.text());
+    }
+
+    /// The source column of the text at a point of a 200pt x 100pt page without margins.
+    fn column_at(body: &str, x: f32, y: f32) -> Option<usize> {
+        let source = format!("#set page(width: 200pt, height: 100pt, margin: 0pt)\n{body}");
+        let page = DocumentPage::new(
+            &source,
+            Vec2::new(200., 100.),
+            &[],
+            Arc::new(Memory::default()),
+            false,
+        )
+        .unwrap();
+        page.source_at(Pos2::new(x, y))
+            .map(|position| position.column)
+    }
+
+    #[test]
+    fn lists_the_imported_sources() {
+        let mut files = Memory::default();
+        files.insert(
+            "sections/work.typ",
+            Bytes::new(b"#let work = [Work]".to_vec()),
+        );
+        files.insert("unused.typ", Bytes::new(b"".to_vec()));
+        let source = "#import \"sections/work.typ\": work\n#work";
+        let page =
+            DocumentPage::new(source, Vec2::new(100., 100.), &[], Arc::new(files), false).unwrap();
+        assert_eq!(page.source_files(), [PathBuf::from("sections/work.typ")]);
+    }
+
+    #[test]
+    fn finds_the_source_of_transformed_content() {
+        // the columns are those of the second line: 2pt into "Moved" is its "M" at column 33
+        let moved = "#place(move(dx: 100pt, dy: 50pt)[Moved])";
+        assert_eq!(column_at(moved, 102., 55.), Some(33));
+        assert_eq!(column_at(moved, 2., 5.), None);
+
+        // "Big" starts at column 39 and is scaled up from the corner, so 45pt is 11.25pt into the
+        // unscaled text, past "B" and "i", which is the "g" at column 41
+        let scaled = "#place(scale(400%, origin: top + left)[Big])";
+        assert_eq!(column_at(scaled, 45., 20.), Some(41));
+        assert_eq!(column_at(scaled, 45., 60.), None);
+    }
+
+    #[test]
+    fn ignores_clipped_content() {
+        let clipped =
+            "#box(width: 10pt, height: 20pt, clip: true)[#box(width: 200pt)[Hidden text]]";
+        assert_eq!(column_at(clipped, 5., 8.), Some(63));
+        assert_eq!(column_at(clipped, 30., 8.), None);
     }
 }
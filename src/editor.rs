@@ -0,0 +1,376 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use egui::{
+    Align, Color32, Id, Rect, TextEdit, TextFormat, TextStyle, Ui, Vec2,
+    text::{CCursor, CCursorRange, LayoutJob},
+};
+use typst::foundations::Bytes;
+
+use crate::{
+    CvView,
+    document::{Diagnostic, SourcePosition},
+    files::{FileProvider, Layered, Memory},
+};
+
+/// How long the editor waits after the last change before the document is compiled again.
+const DEBOUNCE: f64 = 0.4;
+
+/// An editor for the typst sources of a [`CvView`], the view follows the changes while typing.
+///
+/// It edits the main source and the other typst files at the root of the files, the edited files
+/// take precedence over the files of the document until they are saved.
+pub struct SourceEditor {
+    /// The main source first, then the other files by name.
+    files: Vec<SourceFile>,
+    selected: usize,
+    base: Arc<dyn FileProvider>,
+    /// The directory the files are written to when they are saved.
+    #[cfg(not(target_arch = "wasm32"))]
+    save_dir: Option<PathBuf>,
+    last_edit: Option<f64>,
+    /// A line and column of the selected file that the cursor moves to when it is shown next.
+    jump: Option<(usize, usize)>,
+    status: Option<String>,
+}
+
+struct SourceFile {
+    path: PathBuf,
+    text: String,
+    saved: String,
+}
+
+impl SourceFile {
+    fn new(path: PathBuf, text: String) -> Self {
+        Self {
+            path,
+            saved: text.clone(),
+            text,
+        }
+    }
+
+    fn is_modified(&self) -> bool {
+        self.text != self.saved
+    }
+
+    fn title(&self) -> String {
+        let name = self.path.display();
+        if self.is_modified() {
+            format!("{name} *")
+        } else {
+            name.to_string()
+        }
+    }
+
+    /// The character index of a line and column, clamped to the end of the text.
+    fn char_index(&self, line: usize, column: usize) -> usize {
+        let line_start: usize = self
+            .text
+            .split_inclusive('\n')
+            .take(line)
+            .map(|line| line.chars().count())
+            .sum();
+        (line_start + column).min(self.text.chars().count())
+    }
+}
+
+impl SourceEditor {
+    /// Creates the editor for the main `source`, which is shown as `main_path`, and the typst
+    /// files at the root of `files`.
+    ///
+    /// Files in subdirectories are added once the document read them, e.g. with an import, or
+    /// once the cursor moves to them.
+    pub fn new(
+        main_path: impl Into<PathBuf>,
+        source: impl Into<String>,
+        files: Arc<dyn FileProvider>,
+    ) -> Self {
+        let main_path = main_path.into();
+        let mut others: Vec<SourceFile> = files
+            .list(Path::new(""))
+            .into_iter()
+            .filter(|path| *path != main_path && path.extension().is_some_and(|ext| ext == "typ"))
+            .filter_map(|path| {
+                let bytes = files.read(&path).ok()?;
+                let text = std::str::from_utf8(&bytes).ok()?.to_string();
+                Some(SourceFile::new(path, text))
+            })
+            .collect();
+        others.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let mut sources = vec![SourceFile::new(main_path, source.into())];
+        sources.extend(others);
+
+        Self {
+            files: sources,
+            selected: 0,
+            base: files,
+            #[cfg(not(target_arch = "wasm32"))]
+            save_dir: None,
+            last_edit: None,
+            jump: None,
+            status: None,
+        }
+    }
+
+    /// Saves the files to the directory, the paths of the files are relative to it.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save_to(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.save_dir = Some(dir.into());
+        self
+    }
+
+    /// The path the main source is shown as.
+    pub fn main_path(&self) -> &Path {
+        &self.files[0].path
+    }
+
+    /// The current text of the main source.
+    pub fn source(&self) -> &str {
+        &self.files[0].text
+    }
+
+    /// Opens the file of the position and moves the cursor there.
+    ///
+    /// Returns false if the position is in a file that is not edited, e.g. an image or a package.
+    pub fn go_to(&mut self, position: &SourcePosition) -> bool {
+        let index = match &position.path {
+            None => Some(0),
+            Some(path) => self.file_index(path),
+        };
+        let Some(index) = index else {
+            return false;
+        };
+
+        self.selected = index;
+        self.jump = Some((position.line, position.column));
+        true
+    }
+
+    /// The index of an edited file, a typst file of the document that is not edited yet is added.
+    fn file_index(&mut self, path: &Path) -> Option<usize> {
+        if let Some(index) = self.files.iter().position(|file| file.path == path) {
+            return Some(index);
+        }
+        if path.extension().is_none_or(|ext| ext != "typ") {
+            return None;
+        }
+
+        let bytes = self.base.read(path).ok()?;
+        let text = std::str::from_utf8(&bytes).ok()?.to_string();
+
+        // the other files stay sorted after the main source
+        let index = 1 + self.files[1..].partition_point(|file| file.path.as_path() < path);
+        self.files
+            .insert(index, SourceFile::new(path.to_path_buf(), text));
+        if self.selected >= index {
+            self.selected += 1;
+        }
+        Some(index)
+    }
+
+    /// Shows the editor with the diagnostics of the view below it and updates the view once the
+    /// typing stopped.
+    pub fn show(&mut self, ui: &mut Ui, cv: &mut CvView) {
+        let time = ui.input(|i| i.time);
+
+        // imported files in subdirectories are not listed at the start
+        if let Some(document) = cv.document() {
+            for path in document.source_files() {
+                self.file_index(&path);
+            }
+        }
+
+        ui.horizontal(|ui| {
+            let title = self.files[self.selected].title();
+            egui::ComboBox::from_id_salt("source_file")
+                .selected_text(title)
+                .show_ui(ui, |ui| {
+                    for (index, file) in self.files.iter().enumerate() {
+                        ui.selectable_value(&mut self.selected, index, file.title());
+                    }
+                });
+
+            #[cfg(not(target_arch = "wasm32"))]
+            if self.save_dir.is_some() {
+                let modified = self.files.iter().any(SourceFile::is_modified);
+                let save = ui
+                    .add_enabled(modified, egui::Button::new("Save"))
+                    .on_hover_text("Save the changed files (ctrl+s)")
+                    .clicked();
+                let shortcut =
+                    ui.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, egui::Key::S));
+                if save || (modified && shortcut) {
+                    self.save();
+                }
+            }
+        });
+        if let Some(status) = &self.status {
+            ui.colored_label(ui.visuals().error_fg_color, status);
+        }
+
+        // the diagnostics of all files are listed below the editor, a click moves the cursor there
+        let diagnostics = cv.diagnostics().to_vec();
+        let mut clicked = None;
+        egui::TopBottomPanel::bottom("source_diagnostics")
+            .resizable(true)
+            .show_inside(ui, |ui| {
+                if diagnostics.is_empty() {
+                    ui.weak("No errors");
+                }
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for diagnostic in &diagnostics {
+                        let color = diagnostic_color(ui, diagnostic);
+                        let label = egui::RichText::new(diagnostic.to_string()).color(color);
+                        if ui.selectable_label(false, label).clicked() {
+                            clicked = diagnostic.position.clone();
+                        }
+                    }
+                });
+            });
+        if let Some(position) = clicked {
+            self.go_to(&position);
+        }
+
+        let path = (self.selected > 0).then(|| self.files[self.selected].path.clone());
+        let file_diagnostics: Vec<(usize, &Diagnostic)> = diagnostics
+            .iter()
+            .filter_map(|diagnostic| {
+                let position = diagnostic.position.as_ref()?;
+                (position.path == path).then_some((position.line, diagnostic))
+            })
+            .collect();
+        let error_background = ui.visuals().error_fg_color.gamma_multiply(0.2);
+
+        // lines with an error get a background, without wrapping every line is a row of the galley
+        let mut layouter = |ui: &Ui, text: &dyn egui::TextBuffer, _wrap_width: f32| {
+            let font_id = TextStyle::Monospace.resolve(ui.style());
+            let mut job = LayoutJob::default();
+            for (index, line) in text.as_str().split_inclusive('\n').enumerate() {
+                let background = if file_diagnostics
+                    .iter()
+                    .any(|(line, diagnostic)| *line == index && diagnostic.is_error)
+                {
+                    error_background
+                } else {
+                    Color32::TRANSPARENT
+                };
+                job.append(
+                    line,
+                    0.,
+                    TextFormat {
+                        font_id: font_id.clone(),
+                        color: ui.visuals().text_color(),
+                        background,
+                        ..Default::default()
+                    },
+                );
+            }
+            ui.fonts(|fonts| fonts.layout_job(job))
+        };
+
+        let file = &mut self.files[self.selected];
+        let id = Id::new(("source_editor", &file.path));
+        let jump = self.jump.take();
+        let changed = egui::ScrollArea::both()
+            .auto_shrink(false)
+            .show(ui, |ui| {
+                let output = TextEdit::multiline(&mut file.text)
+                    .id(id)
+                    .code_editor()
+                    .desired_width(f32::INFINITY)
+                    .layouter(&mut layouter)
+                    .show(ui);
+
+                // the messages are shown at the end of their lines
+                for (line, diagnostic) in &file_diagnostics {
+                    if let Some(row) = output.galley.rows.get(*line) {
+                        let pos = output.galley_pos + row.rect().right_center().to_vec2();
+                        ui.painter().text(
+                            pos + Vec2::new(24., 0.),
+                            egui::Align2::LEFT_CENTER,
+                            &diagnostic.message,
+                            TextStyle::Monospace.resolve(ui.style()),
+                            diagnostic_color(ui, diagnostic),
+                        );
+                    }
+                }
+
+                if let Some((line, column)) = jump {
+                    let index = file.char_index(line, column);
+                    let mut state = output.state;
+                    state
+                        .cursor
+                        .set_char_range(Some(CCursorRange::one(CCursor::new(index))));
+                    state.store(ui.ctx(), id);
+                    ui.memory_mut(|mem| mem.request_focus(id));
+
+                    if let Some(row) = output.galley.rows.get(line) {
+                        let rect = Rect::from_min_size(
+                            output.galley_pos + row.pos.to_vec2(),
+                            row.rect().size(),
+                        );
+                        ui.scroll_to_rect(rect, Some(Align::Center));
+                    }
+                }
+
+                output.response.changed()
+            })
+            .inner;
+
+        // compile once the typing stopped
+        if changed {
+            self.last_edit = Some(time);
+        }
+        if let Some(last_edit) = self.last_edit {
+            if time - last_edit > DEBOUNCE {
+                self.last_edit = None;
+                self.apply(cv);
+            } else {
+                ui.ctx()
+                    .request_repaint_after(std::time::Duration::from_secs_f64(DEBOUNCE));
+            }
+        }
+    }
+
+    /// Passes the edited files to the view.
+    fn apply(&self, cv: &mut CvView) {
+        let mut edited = Memory::default();
+        for file in &self.files[1..] {
+            edited.insert(
+                file.path.clone(),
+                Bytes::new(file.text.clone().into_bytes()),
+            );
+        }
+        cv.set_source(self.source());
+        cv.set_files(Arc::new(Layered::new(edited, self.base.clone())));
+    }
+
+    /// Writes the changed files to the save directory.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn save(&mut self) {
+        let Some(dir) = &self.save_dir else {
+            return;
+        };
+
+        for file in self.files.iter_mut().filter(|file| file.is_modified()) {
+            let path = dir.join(&file.path);
+            if let Err(e) = std::fs::write(&path, &file.text) {
+                self.status = Some(format!("Could not save {}: {e}", path.display()));
+                return;
+            }
+            file.saved = file.text.clone();
+        }
+        self.status = None;
+    }
+}
+
+fn diagnostic_color(ui: &Ui, diagnostic: &Diagnostic) -> Color32 {
+    if diagnostic.is_error {
+        ui.visuals().error_fg_color
+    } else {
+        ui.visuals().warn_fg_color
+    }
+}
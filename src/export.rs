@@ -0,0 +1,51 @@
+/// Saves the bytes as a file, with a save dialog on native and as a browser download on the web.
+///
+/// Returns an error message if the file could not be written, a cancelled dialog is not an error.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_file(file_name: &str, _mime_type: &str, bytes: &[u8]) -> Result<(), String> {
+    let Some(path) = rfd::FileDialog::new().set_file_name(file_name).save_file() else {
+        return Ok(());
+    };
+
+    std::fs::write(&path, bytes).map_err(|e| format!("Could not write {}: {e}", path.display()))
+}
+
+/// Saves the bytes as a file, with a save dialog on native and as a browser download on the web.
+///
+/// Returns an error message if the file could not be written, a cancelled dialog is not an error.
+#[cfg(target_arch = "wasm32")]
+pub fn save_file(file_name: &str, mime_type: &str, bytes: &[u8]) -> Result<(), String> {
+    use eframe::wasm_bindgen::JsCast as _;
+
+    let error = |e: eframe::wasm_bindgen::JsValue| format!("Could not download {file_name}: {e:?}");
+
+    // wrap the bytes in a blob and click a temporary link to it
+    let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(bytes));
+    let options = web_sys::BlobPropertyBag::new();
+    options.set_type(mime_type);
+    let blob =
+        web_sys::Blob::new_with_u8_array_sequence_and_options(&parts, &options).map_err(error)?;
+    let url = web_sys::Url::create_object_url_with_blob(&blob).map_err(error)?;
+
+    let document = web_sys::window()
+        .and_then(|window| window.document())
+        .ok_or("No document")?;
+    let link = document
+        .create_element("a")
+        .map_err(error)?
+        .dyn_into::<web_sys::HtmlAnchorElement>()
+        .map_err(|_| "Not an anchor element")?;
+    link.set_href(&url);
+    link.set_download(file_name);
+    link.click();
+
+    // the download starts asynchronously, so the url is only revoked once it has begun
+    let revoke = eframe::wasm_bindgen::closure::Closure::once_into_js(move || {
+        web_sys::Url::revoke_object_url(&url).ok();
+    });
+    web_sys::window()
+        .ok_or("No window")?
+        .set_timeout_with_callback_and_timeout_and_arguments_0(revoke.unchecked_ref(), 1000)
+        .map(|_| ())
+        .map_err(error)
+}
@@ -0,0 +1,195 @@
+use std::path::{Path, PathBuf};
+
+use typst::{
+    diag::{FileError, FileResult},
+    foundations::Bytes,
+};
+
+/// Access to the files of a document, like images, data, fonts and packages.
+///
+/// Paths are relative to the root of the provider and use `/` as separator. The provider is shared
+/// by all compilations of a document.
+pub trait FileProvider: Send + Sync {
+    /// Reads a file, a missing file is a [`FileError::NotFound`].
+    fn read(&self, path: &Path) -> FileResult<Bytes>;
+
+    /// Lists the files directly inside a directory, empty if the directory doesn't exist.
+    fn list(&self, dir: &Path) -> Vec<PathBuf>;
+}
+
+/// Files in a directory on disk.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct Filesystem {
+    root: PathBuf,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Filesystem {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl FileProvider for Filesystem {
+    fn read(&self, path: &Path) -> FileResult<Bytes> {
+        let path = self.root.join(path);
+        std::fs::read(&path)
+            .map(Bytes::new)
+            .map_err(|error| FileError::from_io(error, &path))
+    }
+
+    fn list(&self, dir: &Path) -> Vec<PathBuf> {
+        let Ok(entries) = std::fs::read_dir(self.root.join(dir)) else {
+            return Vec::new();
+        };
+        entries
+            .flatten()
+            .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_file()))
+            .map(|entry| dir.join(entry.file_name()))
+            .collect()
+    }
+}
+
+/// Files embedded in the binary with `include_dir!`.
+#[cfg(target_arch = "wasm32")]
+pub struct Embedded {
+    dir: include_dir::Dir<'static>,
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Embedded {
+    pub fn new(dir: include_dir::Dir<'static>) -> Self {
+        Self { dir }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl FileProvider for Embedded {
+    fn read(&self, path: &Path) -> FileResult<Bytes> {
+        self.dir
+            .get_file(path)
+            .map(|file| Bytes::new(file.contents()))
+            .ok_or_else(|| FileError::NotFound(path.into()))
+    }
+
+    fn list(&self, dir: &Path) -> Vec<PathBuf> {
+        // the root is not an entry of itself
+        let dir = if dir.as_os_str().is_empty() {
+            Some(&self.dir)
+        } else {
+            self.dir.get_dir(dir)
+        };
+        dir.map(|dir| dir.files().map(|file| file.path().to_path_buf()).collect())
+            .unwrap_or_default()
+    }
+}
+
+/// Files kept in memory, e.g. generated or edited ones.
+#[derive(Default)]
+pub struct Memory {
+    files: std::collections::HashMap<PathBuf, Bytes>,
+}
+
+impl Memory {
+    /// Adds a file or replaces the file at the same path.
+    pub fn insert(&mut self, path: impl Into<PathBuf>, bytes: Bytes) {
+        self.files.insert(path.into(), bytes);
+    }
+}
+
+impl FileProvider for Memory {
+    fn read(&self, path: &Path) -> FileResult<Bytes> {
+        self.files
+            .get(path)
+            .cloned()
+            .ok_or_else(|| FileError::NotFound(path.into()))
+    }
+
+    fn list(&self, dir: &Path) -> Vec<PathBuf> {
+        self.files
+            .keys()
+            .filter(|path| path.parent() == Some(dir))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Files of a tar archive, optionally gzip compressed like the packages of typst.
+///
+/// The archive is unpacked into memory once.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct Archive {
+    files: Memory,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Archive {
+    pub fn new(bytes: &[u8]) -> Result<Self, String> {
+        let error = |e: std::io::Error| format!("Could not read the archive: {e}");
+
+        // gzip streams start with a magic number
+        let reader: Box<dyn std::io::Read + '_> = if bytes.starts_with(&[0x1f, 0x8b]) {
+            Box::new(flate2::read::GzDecoder::new(bytes))
+        } else {
+            Box::new(bytes)
+        };
+
+        let mut files = Memory::default();
+        let mut archive = tar::Archive::new(reader);
+        for entry in archive.entries().map_err(error)? {
+            let mut entry = entry.map_err(error)?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+
+            // archives often store their paths as `./file`
+            let path = entry.path().map_err(error)?;
+            let path: PathBuf = path.components().filter(|c| c.as_os_str() != ".").collect();
+            let mut content = Vec::new();
+            std::io::Read::read_to_end(&mut entry, &mut content).map_err(error)?;
+            files.insert(path, Bytes::new(content));
+        }
+
+        Ok(Self { files })
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl FileProvider for Archive {
+    fn read(&self, path: &Path) -> FileResult<Bytes> {
+        self.files.read(path)
+    }
+
+    fn list(&self, dir: &Path) -> Vec<PathBuf> {
+        self.files.list(dir)
+    }
+}
+
+/// Files that take precedence over the files of another provider, e.g. unsaved edits.
+pub struct Layered {
+    top: Memory,
+    base: std::sync::Arc<dyn FileProvider>,
+}
+
+impl Layered {
+    pub fn new(top: Memory, base: std::sync::Arc<dyn FileProvider>) -> Self {
+        Self { top, base }
+    }
+}
+
+impl FileProvider for Layered {
+    fn read(&self, path: &Path) -> FileResult<Bytes> {
+        self.top.read(path).or_else(|_| self.base.read(path))
+    }
+
+    fn list(&self, dir: &Path) -> Vec<PathBuf> {
+        let mut files = self.base.list(dir);
+        for path in self.top.list(dir) {
+            if !files.contains(&path) {
+                files.push(path);
+            }
+        }
+        files
+    }
+}
@@ -0,0 +1,20 @@
+//! A cv written in typst, rendered with egui and enhanced with popups for its labelled blocks.
+//!
+//! [`CvView`] is the widget that shows the cv, it can be placed anywhere in the layout of an egui
+//! app. The `living_cv` binary is a thin wrapper around it.
+
+pub mod contact;
+pub mod cv_view;
+pub mod document;
+pub mod editor;
+pub mod files;
+pub mod overlay;
+pub mod popup;
+pub mod qr;
+pub mod resume;
+pub mod tags;
+pub mod theme;
+pub mod timeline;
+pub mod view;
+
+pub use cv_view::CvView;
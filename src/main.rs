@@ -1,10 +1,20 @@
 mod app;
-mod document;
-mod overlay;
-mod popup;
+#[cfg(not(target_arch = "wasm32"))]
+mod cli;
+mod deep_link;
+mod export;
 
 #[cfg(not(target_arch = "wasm32"))]
 fn main() -> eframe::Result {
+    // the system fonts can be turned off in the popup data for the same output on every machine
+    let system_fonts = app::system_fonts(&app::popup_file().0);
+
+    // the headless commands run without opening a window
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(exit_code) = cli::run(&args, system_fonts) {
+        std::process::exit(exit_code);
+    }
+
     let native_options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_inner_size([400.0, 300.0])
@@ -17,7 +27,11 @@ fn main() -> eframe::Result {
         native_options,
         Box::new(|cc| {
             egui_extras::install_image_loaders(&cc.egui_ctx);
-            Ok(Box::new(app::App::new(cc)))
+            Ok(Box::new(app::App::new(
+                cc,
+                deep_link::open_argument(),
+                system_fonts,
+            )))
         }),
     )
 }
@@ -50,7 +64,12 @@ fn main() {
                 web_options,
                 Box::new(|cc| {
                     egui_extras::install_image_loaders(&cc.egui_ctx);
-                    Ok(Box::new(app::App::new(cc)))
+                    // the browser has no system fonts
+                    Ok(Box::new(app::App::new(
+                        cc,
+                        deep_link::current_fragment(),
+                        false,
+                    )))
                 }),
             )
             .await;
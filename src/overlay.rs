@@ -1,26 +1,79 @@
-use egui::{Color32, Rect, Response, Sense, Shape, Ui, Vec2, Widget};
+use egui::{
+    EventFilter, Id, Rect, Response, Sense, Shape, Ui, Widget, WidgetInfo, WidgetType, emath,
+};
 
-use crate::popup::Popup;
+use crate::{
+    popup::{Placement, Popup},
+    theme::OverlayStyle,
+    view::View,
+};
 
 pub struct Overlay {
+    id: Id,
+    page_rect: Rect,
     hover_rect: Rect,
     is_popup_visible: bool,
     label: String,
+    text: String,
     popup: Option<Popup>,
+    style: OverlayStyle,
+    pulse_start: Option<f64>,
+    tags: Vec<String>,
+    highlighted: bool,
 }
 
 impl Overlay {
-    pub fn new(rect: Rect, label: String, panel_size: Vec2) -> Self {
-        let popup = Popup::new(&label, panel_size);
-
+    pub fn new(
+        rect: Rect,
+        label: String,
+        text: String,
+        style: OverlayStyle,
+        popup: Option<Popup>,
+        tags: Vec<String>,
+    ) -> Self {
         Self {
+            id: Id::new(("overlay", &label)),
+            page_rect: rect,
             hover_rect: rect,
             is_popup_visible: false,
             label,
+            text,
             popup,
+            style,
+            pulse_start: None,
+            tags,
+            highlighted: false,
         }
     }
 
+    /// Moves the hover rectangle with the zoom and pan of the page.
+    pub fn set_view(&mut self, view: &View) {
+        self.hover_rect = view.transform_rect(self.page_rect);
+    }
+
+    /// The stable widget id, used for the keyboard focus.
+    pub fn id(&self) -> Id {
+        self.id
+    }
+
+    pub fn hover_rect(&self) -> Rect {
+        self.hover_rect
+    }
+
+    /// Highlights the block with a short pulse, e.g. as the target of a deep link.
+    pub fn pulse(&mut self, time: f64) {
+        self.pulse_start = Some(time);
+    }
+
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    /// Keeps the block highlighted like a hovered one, e.g. when it matches the tag filter.
+    pub fn set_highlighted(&mut self, highlighted: bool) {
+        self.highlighted = highlighted;
+    }
+
     pub fn has_popup(&self) -> bool {
         self.popup.is_some()
     }
@@ -31,8 +84,19 @@ impl Overlay {
         }
     }
 
+    /// Shows the popup as a floating window that stays open next to other pinned popups.
+    pub fn pin_popup(&mut self) {
+        if let Some(popup) = self.popup.as_mut() {
+            popup.pin();
+            self.is_popup_visible = true;
+        }
+    }
+
     pub fn hide_popup(&mut self) {
         self.is_popup_visible = false;
+        if let Some(popup) = self.popup.as_mut() {
+            popup.unpin();
+        }
     }
 
     pub fn is_popup_visible(&self) -> bool {
@@ -43,50 +107,143 @@ impl Overlay {
         self.popup.as_mut()
     }
 
+    pub fn placement(&self) -> Option<Placement> {
+        self.popup.as_ref().map(|popup| popup.placement())
+    }
+
     pub fn label(&self) -> String {
         self.label.clone()
     }
+
+    /// Paints a ring that grows and fades out, skipped with reduced motion.
+    fn paint_pulse(&mut self, ui: &Ui) {
+        let Some(start) = self.pulse_start else {
+            return;
+        };
+
+        let duration = 10. * ui.style().animation_time;
+        let t = ((ui.input(|i| i.time) - start) as f32 / duration).min(1.);
+        if duration <= 0. || t >= 1. {
+            self.pulse_start = None;
+            return;
+        }
+
+        let color = self.style.hover_stroke.gamma_multiply(1. - t);
+        ui.painter().add(Shape::rect_stroke(
+            self.hover_rect.expand(12. * t),
+            self.style.corner_radius,
+            egui::Stroke::new(3., color),
+            egui::StrokeKind::Outside,
+        ));
+        ui.ctx().request_repaint();
+    }
 }
 
 impl Widget for &mut Overlay {
     fn ui(self, ui: &mut Ui) -> Response {
-        // allocate the hover rectangle that enables the interaction
-        let resp = ui.allocate_rect(self.hover_rect, Sense::click());
+        // the hover rectangle enables the interaction, the stable id keeps the keyboard focus
+        let resp = ui.interact(self.hover_rect, self.id, Sense::click());
+        let focused = resp.has_focus();
+
+        // publish the block to screen readers, with its text as description
+        let widget_type = if self.has_popup() {
+            WidgetType::Button
+        } else {
+            WidgetType::Other
+        };
+        resp.widget_info(|| WidgetInfo::labeled(widget_type, true, &self.label));
+        ui.ctx().accesskit_node_builder(self.id, |node| {
+            node.set_description(self.text.as_str());
+        });
+
+        if focused {
+            // the app moves the focus between the blocks with the arrow keys, escape closes the popup
+            let filter = EventFilter {
+                horizontal_arrows: true,
+                vertical_arrows: true,
+                escape: true,
+                ..Default::default()
+            };
+            ui.memory_mut(|mem| mem.set_focus_lock_filter(self.id, filter));
+        }
 
-        // always draw the rectangle, but filled and with thicker stroke when hovered
+        // draw the rectangle, fading in the fill and the thicker stroke when hovered or highlighted
+        let hover = ui.ctx().animate_bool(
+            self.id.with("hover"),
+            resp.contains_pointer() || self.highlighted,
+        );
         if ui.is_rect_visible(self.hover_rect) {
-            let mut stroke_width = 0.5;
-            let mut stroke_color = Color32::from_hex("#aaaaaa").unwrap_or(Color32::LIGHT_BLUE);
-            let corner_radius = 5.;
+            let style = &self.style;
 
-            if resp.contains_pointer() {
+            if hover > 0. {
                 ui.painter().add(Shape::rect_filled(
                     self.hover_rect,
-                    corner_radius,
-                    Color32::from_hex("#09a7cb11").unwrap_or(Color32::LIGHT_BLUE),
+                    style.corner_radius,
+                    style.hover_fill.gamma_multiply(hover),
                 ));
+            }
 
-                // change the stroke width for the hovered rect
-                stroke_width = 2.;
-                stroke_color = Color32::from_hex("#09a7cb").unwrap_or(Color32::LIGHT_BLUE);
+            // hidden outlines fade in from transparent instead of the normal stroke
+            let stroke = if style.hide_until_hover {
+                egui::Stroke::new(
+                    style.hover_stroke_width,
+                    style.hover_stroke.gamma_multiply(hover),
+                )
+            } else {
+                egui::Stroke::new(
+                    emath::lerp(style.stroke_width..=style.hover_stroke_width, hover),
+                    style.stroke.lerp_to_gamma(style.hover_stroke, hover),
+                )
+            };
+            if stroke.color.a() > 0 {
+                ui.painter().add(Shape::rect_stroke(
+                    self.hover_rect,
+                    style.corner_radius,
+                    stroke,
+                    egui::StrokeKind::Inside,
+                ));
             }
 
-            ui.painter().add(Shape::rect_stroke(
-                self.hover_rect,
-                corner_radius,
-                egui::Stroke::new(stroke_width, stroke_color),
-                egui::StrokeKind::Inside,
-            ));
+            // draw a ring around the block that has the keyboard focus
+            if focused {
+                let focus_stroke = egui::Stroke::new(2., ui.visuals().selection.bg_fill);
+                ui.painter().add(Shape::rect_stroke(
+                    self.hover_rect,
+                    style.corner_radius,
+                    focus_stroke,
+                    egui::StrokeKind::Outside,
+                ));
+            }
         }
 
-        // open the modal window
-        if resp.clicked() {
+        self.paint_pulse(ui);
+
+        // open the popup, side panel popups are shown by the app instead unless they are pinned
+        let pinned = self.popup.as_ref().is_some_and(|popup| popup.is_pinned());
+        if resp.clicked() && (pinned || self.placement() != Some(Placement::SidePanel)) {
             self.show_popup();
         }
 
+        // escape closes the popup of the focused block first and releases the focus after that
+        if focused && ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+            if self.is_popup_visible() {
+                self.hide_popup();
+            } else {
+                ui.memory_mut(|mem| mem.surrender_focus(self.id));
+            }
+        }
+
+        // fade in the popup, the animation has to be updated while it is hidden as well
+        let opacity = ui
+            .ctx()
+            .animate_bool(self.id.with("popup"), self.is_popup_visible());
         if self.is_popup_visible() {
-            let modal = self.popup().unwrap().show(ui);
-            if modal.should_close() {
+            let hover_rect = self.hover_rect;
+            let should_close = self
+                .popup()
+                .unwrap()
+                .show(ui, hover_rect, resp.clicked(), opacity);
+            if should_close {
                 self.hide_popup();
             }
         }
@@ -1,20 +1,183 @@
 use std::collections::HashMap;
 
-use egui::{Hyperlink, ImageSource, Vec2};
+use egui::{Hyperlink, ImageSource, Pos2, Rect, Vec2};
+
+use crate::{contact::Contact, qr, theme::PopupStyle};
+
+/// Picks a variant and a language of the popup data, which is shared by the popups and the theme.
+///
+/// The tables of the `[variant.<variant>]` and then the `[lang.<language>]` section replace the
+/// top-level tables of the same name, so only the blocks that differ have to be repeated there.
+pub fn localize(mut data: toml::Table, variant: &str, language: &str) -> toml::Table {
+    for (section, name) in [("variant", variant), ("lang", language)] {
+        if let Some(toml::Value::Table(sections)) = data.remove(section)
+            && let Some(toml::Value::Table(tables)) = sections.get(name)
+        {
+            data.extend(tables.clone());
+        }
+    }
+
+    data
+}
+
+/// The table of the popup data file that the given block is taken from by [`localize`]: the
+/// language section, then the variant section, then the top level.
+pub fn block_section<'a>(
+    data: &'a mut toml::Table,
+    label: &str,
+    variant: &str,
+    language: &str,
+) -> &'a mut toml::Table {
+    let found = [("lang", language), ("variant", variant)]
+        .into_iter()
+        .find(|(section, name)| {
+            data.get(*section)
+                .and_then(|sections| sections.get(*name))
+                .is_some_and(|tables| tables.is_table() && tables.get(label).is_some())
+        });
+    let Some((section, name)) = found else {
+        return data;
+    };
+
+    data.get_mut(section)
+        .and_then(|sections| sections.get_mut(name))
+        .and_then(|tables| tables.as_table_mut())
+        .expect("the section was found above")
+}
+
+/// Replaces the items of a block in the popup data, the block is added if it is missing.
+pub fn set_items(data: &mut toml::Table, label: &str, items: &[(String, String)]) {
+    let items = items
+        .iter()
+        .map(|(kind, value)| {
+            let mut item = toml::Table::new();
+            item.insert(kind.clone(), toml::Value::String(value.clone()));
+            toml::Value::Table(item)
+        })
+        .collect();
+
+    let block = data
+        .entry(label)
+        .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+    if !block.is_table() {
+        *block = toml::Value::Table(toml::Table::new());
+    }
+    if let Some(block) = block.as_table_mut() {
+        block.insert("items".to_string(), toml::Value::Array(items));
+    }
+}
+
+/// Returns the languages listed in the popup data, the first one is the default.
+pub fn languages(popup_data: Option<&toml::Table>) -> Vec<String> {
+    string_list(popup_data, "languages")
+}
+
+/// Returns the cv variants listed in the popup data, the first one is the default.
+pub fn variants(popup_data: Option<&toml::Table>) -> Vec<String> {
+    string_list(popup_data, "variants")
+}
+
+fn string_list(popup_data: Option<&toml::Table>, key: &str) -> Vec<String> {
+    popup_data
+        .and_then(|data| data.get(key))
+        .and_then(|v| v.as_array())
+        .map(|values| {
+            values
+                .iter()
+                .filter_map(|v| v.as_str().map(String::from))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Where the popup of a block is shown once the block is clicked.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Placement {
+    /// Centered modal that blocks the rest of the CV.
+    #[default]
+    Modal,
+    /// Floating next to the clicked block, flipped to stay on screen.
+    Anchored,
+    /// Docked to the right side of the window, the CV stays visible.
+    SidePanel,
+    /// Draggable and resizable window, several of them can be open at once.
+    Window,
+}
+
+impl Placement {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "modal" => Some(Self::Modal),
+            "anchored" => Some(Self::Anchored),
+            "side_panel" => Some(Self::SidePanel),
+            "window" => Some(Self::Window),
+            _ => None,
+        }
+    }
+}
+
+/// The top-level keys of the popup data that are settings, they can't be used as block labels.
+pub const RESERVED_KEYS: [&str; 9] = [
+    "languages",
+    "variants",
+    "lang",
+    "variant",
+    "theme",
+    "tags",
+    "placement",
+    "url",
+    "system_fonts",
+];
+
+/// The item types of a popup, by their key in the popup data.
+pub const ITEM_TYPES: [&str; 4] = ["label", "link", "image", "qr"];
 
 pub struct Popup {
     data: Vec<(String, String)>,
     panel_size: Vec2,
     images: HashMap<String, ImageSource<'static>>,
+    placement: Placement,
+    pinned: bool,
+    label: String,
+    id: egui::Id,
+    style: PopupStyle,
+    /// The url of the live cv, for the QR codes.
+    cv_url: Option<String>,
+    /// The QR codes as svg by their url, generated once instead of on every frame.
+    qr_codes: HashMap<String, Result<egui::load::Bytes, String>>,
+    /// The contact details of the block, which can be saved as a vCard.
+    contact: Option<Contact>,
+    save_contact: bool,
+    /// Shows an editor for the items above them.
+    authoring: bool,
+    edited: bool,
 }
 
 impl Popup {
-    pub fn new(label: &String, panel_size: Vec2) -> Option<Self> {
-        let file = include_str!("../popup.toml");
-        let readin = file.parse::<toml::Table>().ok()?;
+    pub fn new(
+        label: &String,
+        panel_size: Vec2,
+        style: PopupStyle,
+        readin: &toml::Table,
+        images: &HashMap<String, ImageSource<'static>>,
+    ) -> Self {
+        // the global placement applies to every block without its own setting
+        let mut placement = readin
+            .get("placement")
+            .and_then(|v| v.as_str())
+            .and_then(Placement::parse)
+            .unwrap_or_default();
 
         let mut data = Vec::default();
         if let Some(items) = readin.get(label) {
+            if let Some(block_placement) = items
+                .get("placement")
+                .and_then(|v| v.as_str())
+                .and_then(Placement::parse)
+            {
+                placement = block_placement;
+            }
+
             if let Some(item) = items.get("items") {
                 for val in item.as_array().unwrap() {
                     for (key, v) in val.as_table().unwrap() {
@@ -24,87 +187,367 @@ impl Popup {
             }
         }
 
-        // hardcode the image paths for now
-        let mut images: HashMap<String, ImageSource<'static>> = HashMap::new();
-        images.insert(
-            String::from("header.jpg"),
-            egui::include_image!("../assets/images/header.jpg"),
-        );
-
-        if data.is_empty() {
-            None
-        } else {
-            Some(Self {
-                data,
-                panel_size,
-                images,
-            })
+        Self {
+            data,
+            panel_size,
+            images: images.clone(),
+            placement,
+            pinned: false,
+            label: label.clone(),
+            id: egui::Id::new(("popup", label)),
+            style,
+            cv_url: qr::cv_url(Some(readin)),
+            qr_codes: HashMap::new(),
+            contact: None,
+            save_contact: false,
+            authoring: false,
+            edited: false,
         }
     }
 
+    /// Whether the popup has neither items nor contact details, such popups are only shown for
+    /// authoring.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty() && self.contact.is_none()
+    }
+
     pub fn data(&self) -> &Vec<(String, String)> {
         &self.data
     }
+
+    pub fn placement(&self) -> Placement {
+        self.placement
+    }
+
+    /// Turns the popup into a floating window, independent of its configured placement.
+    pub fn pin(&mut self) {
+        self.pinned = true;
+    }
+
+    pub fn unpin(&mut self) {
+        self.pinned = false;
+    }
+
+    pub fn is_pinned(&self) -> bool {
+        self.pinned
+    }
+
+    pub fn set_authoring(&mut self, authoring: bool) {
+        self.authoring = authoring;
+    }
+
+    /// Adds a "Save contact" button below the items.
+    pub fn set_contact(&mut self, contact: Option<Contact>) {
+        self.contact = contact;
+    }
+
+    /// The contact details if "Save contact" was clicked since the last call.
+    pub fn take_saved_contact(&mut self) -> Option<Contact> {
+        std::mem::take(&mut self.save_contact)
+            .then(|| self.contact.clone())
+            .flatten()
+    }
+
+    /// Whether the items were edited since the last call.
+    pub fn take_edited(&mut self) -> bool {
+        std::mem::take(&mut self.edited)
+    }
 }
 
 impl Popup {
-    pub fn show(&mut self, ui: &mut egui::Ui) -> egui::ModalResponse<()> {
-        let framestyle = egui::containers::Frame {
-            inner_margin: egui::epaint::Margin {
-                left: 2,
-                right: 2,
-                top: 2,
-                bottom: 2,
-            },
-            outer_margin: egui::epaint::Margin {
-                left: 0,
-                right: 0,
-                top: 0,
-                bottom: 0,
-            },
-            corner_radius: egui::CornerRadius {
-                nw: 8,
-                ne: 8,
-                sw: 8,
-                se: 8,
-            },
-            shadow: eframe::epaint::Shadow {
-                color: egui::Color32::from_hex("#000000aa").unwrap(),
-                offset: [10, 10],
-                blur: 20,
-                spread: 10,
-            },
-            fill: egui::Color32::from_hex("#ffffff").unwrap(),
-            stroke: egui::Stroke::new(2.0, egui::Color32::from_hex("#266590").unwrap()),
+    /// Shows the popup as modal, window or anchored to `anchor`, depending on its placement.
+    /// Returns true if the popup should be closed.
+    ///
+    /// Side panel popups are drawn by the app, as panels have to be added before the central panel.
+    ///
+    /// The popup fades in with `opacity`, windows use their own fade of egui.
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        anchor: Rect,
+        just_opened: bool,
+        opacity: f32,
+    ) -> bool {
+        let placement = if self.pinned {
+            Placement::Window
+        } else {
+            self.placement
         };
-        egui::Modal::new(egui::Id::new("modal"))
-            .frame(framestyle)
+
+        match placement {
+            Placement::Modal => {
+                // the frame is drawn inside the modal to fade it in with the content
+                let modal = egui::Modal::new(self.id.with("modal"))
+                    .frame(egui::Frame::NONE)
+                    .show(ui.ctx(), |ui| {
+                        ui.multiply_opacity(opacity);
+                        self.style.frame().show(ui, |ui| {
+                            self.pin_button(ui);
+                            self.show_contents(ui);
+                        });
+                    });
+                modal.should_close() && !self.pinned
+            }
+            Placement::Anchored => self.show_anchored(ui, anchor, just_opened, opacity),
+            Placement::Window => self.show_window(ui, anchor),
+            Placement::SidePanel => false,
+        }
+    }
+
+    /// Small button that pins the popup as a window, so it stays open next to others.
+    pub fn pin_button(&mut self, ui: &mut egui::Ui) {
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Min), |ui| {
+            if ui
+                .small_button("📌")
+                .on_hover_text("Pin as window")
+                .clicked()
+            {
+                self.pin();
+            }
+        });
+    }
+
+    fn show_window(&mut self, ui: &mut egui::Ui, anchor: Rect) -> bool {
+        let mut open = true;
+        let window = egui::Window::new(&self.label)
+            .id(self.id.with("window"))
+            .open(&mut open)
+            .resizable(true)
+            .collapsible(false)
+            .default_pos(Pos2::new(anchor.right() + 8., anchor.top()))
+            .frame(self.style.frame().inner_margin(6))
             .show(ui.ctx(), |ui| {
-                for (key, value) in &self.data {
-                    match key.as_str() {
-                        "image" => {
-                            if let Some(img) = self.images.get(value) {
-                                ui.add(
-                                    egui::Image::new(img.clone())
-                                        .corner_radius(5)
-                                        .maintain_aspect_ratio(true)
-                                        .max_width(&self.panel_size.x * 0.7)
-                                        .max_height(&self.panel_size.y * 0.7)
-                                        .fit_to_fraction(Vec2::from((2.0, 2.0))),
-                                );
-                            };
-                        }
-                        "label" => {
-                            ui.label(value);
-                        }
-                        "link" => {
+                egui::ScrollArea::vertical().show(ui, |ui| self.show_contents(ui));
+            });
+
+        // link the window to its source block with a leader line below all windows
+        if let Some(window) = window {
+            let window_rect = window.response.rect;
+            if !window_rect.intersects(anchor) {
+                let start = anchor.clamp(window_rect.center());
+                let end = window_rect.clamp(anchor.center());
+                let stroke = egui::Stroke::new(1.5, self.style.stroke);
+                ui.painter().line_segment([start, end], stroke);
+                ui.painter().circle_filled(start, 3., stroke.color);
+            }
+        }
+
+        !open
+    }
+
+    fn show_anchored(
+        &mut self,
+        ui: &mut egui::Ui,
+        anchor: Rect,
+        just_opened: bool,
+        opacity: f32,
+    ) -> bool {
+        let id = self.id.with("anchored");
+        let screen = ui.ctx().screen_rect();
+        let gap = 8.;
+
+        // use the size of the last frame to decide on which side of the block the popup fits
+        let size = ui
+            .ctx()
+            .memory(|mem| mem.area_rect(id))
+            .map(|rect| rect.size())
+            .unwrap_or_default();
+
+        let mut pos = Pos2::new(anchor.right() + gap, anchor.top());
+        if pos.x + size.x > screen.right() {
+            pos.x = anchor.left() - gap - size.x;
+        }
+        pos.x = pos.x.max(screen.left());
+        pos.y = pos.y.min(screen.bottom() - size.y).max(screen.top());
+
+        let area = egui::Area::new(id)
+            .order(egui::Order::Foreground)
+            .fixed_pos(pos)
+            .constrain(true)
+            .show(ui.ctx(), |ui| {
+                ui.multiply_opacity(opacity);
+                self.style.frame().show(ui, |ui| {
+                    self.pin_button(ui);
+                    self.show_contents(ui);
+                });
+            });
+        if self.pinned {
+            return false;
+        }
+
+        // the click that opened the popup is not on the popup itself, so ignore it, the menus of
+        // the editor are outside of the popup as well
+        let clicked_elsewhere =
+            !just_opened && !self.authoring && area.response.clicked_elsewhere();
+        clicked_elsewhere || ui.input(|i| i.key_pressed(egui::Key::Escape))
+    }
+
+    /// Draws the items of the popup into the given ui, independent of the container.
+    ///
+    /// For authoring, the editor of the items is shown above them, so they are a live preview.
+    pub fn show_contents(&mut self, ui: &mut egui::Ui) {
+        // group the items as a dialog named after the block for screen readers
+        ui.ctx().accesskit_node_builder(ui.unique_id(), |node| {
+            node.set_role(egui::accesskit::Role::Dialog);
+            node.set_label(self.label.as_str());
+        });
+
+        if self.authoring {
+            self.show_editor(ui);
+            ui.separator();
+        }
+
+        for (key, value) in &self.data {
+            match key.as_str() {
+                "image" => {
+                    // images registered with the cv, or loaded from a url like `https://...`
+                    let image = self.images.get(value).cloned().or_else(|| {
+                        value
+                            .contains("://")
+                            .then(|| ImageSource::Uri(value.clone().into()))
+                    });
+                    if let Some(img) = image {
+                        ui.add(
+                            egui::Image::new(img)
+                                .alt_text(value)
+                                .corner_radius(5)
+                                .maintain_aspect_ratio(true)
+                                .max_width(&self.panel_size.x * 0.7)
+                                .max_height(&self.panel_size.y * 0.7)
+                                .fit_to_fraction(Vec2::from((2.0, 2.0))),
+                        );
+                    };
+                }
+                "label" => {
+                    ui.label(value);
+                }
+                "link" => {
+                    ui.add(Hyperlink::from_label_and_url(value, value).open_in_new_tab(true));
+                }
+                "qr" => {
+                    // the value is the label of a block, empty for the whole cv, or a url
+                    let url = if value.contains("://") {
+                        Some(value.clone())
+                    } else {
+                        self.cv_url
+                            .as_deref()
+                            .map(|cv_url| qr::block_url(cv_url, value))
+                    };
+                    let svg = url.map(|url| {
+                        let svg = self.qr_codes.entry(url.clone()).or_insert_with(|| {
+                            qr::svg(&url).map(|svg| egui::load::Bytes::from(svg.into_bytes()))
+                        });
+                        (url, svg)
+                    });
+                    match svg {
+                        Some((url, Ok(svg))) => {
+                            let uri = format!("bytes://qr-{}.svg", egui::Id::new(&url).value());
                             ui.add(
-                                Hyperlink::from_label_and_url(value, value).open_in_new_tab(true),
+                                egui::Image::from_bytes(uri, svg.clone())
+                                    .alt_text(format!("QR code of {url}"))
+                                    .max_width(160.),
                             );
                         }
-                        _ => (),
+                        Some((_, Err(error))) => {
+                            ui.colored_label(ui.visuals().error_fg_color, error);
+                        }
+                        None => {
+                            ui.weak("No url for the QR code, set `url` in the popup data");
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        if self.contact.is_some()
+            && ui
+                .button("Save contact")
+                .on_hover_text("Save the contact details as a vCard")
+                .clicked()
+        {
+            self.save_contact = true;
+        }
+    }
+
+    /// Edits the type and the value of the items, reorders, removes and adds them.
+    fn show_editor(&mut self, ui: &mut egui::Ui) {
+        let mut image_names: Vec<&String> = self.images.keys().collect();
+        image_names.sort();
+
+        let count = self.data.len();
+        let mut swap = None;
+        let mut remove = None;
+        let mut changed = false;
+        egui::Grid::new(self.id.with("editor"))
+            .num_columns(4)
+            .show(ui, |ui| {
+                for (index, (kind, value)) in self.data.iter_mut().enumerate() {
+                    ui.horizontal(|ui| {
+                        let up = egui::Button::new("⬆").small();
+                        if ui
+                            .add_enabled(index > 0, up)
+                            .on_hover_text("Move up")
+                            .clicked()
+                        {
+                            swap = Some(index - 1);
+                        }
+                        let down = egui::Button::new("⬇").small();
+                        if ui
+                            .add_enabled(index + 1 < count, down)
+                            .on_hover_text("Move down")
+                            .clicked()
+                        {
+                            swap = Some(index);
+                        }
+                    });
+
+                    egui::ComboBox::from_id_salt(self.id.with(("type", index)))
+                        .selected_text(kind.as_str())
+                        .show_ui(ui, |ui| {
+                            for item_type in ITEM_TYPES {
+                                changed |= ui
+                                    .selectable_value(kind, item_type.to_string(), item_type)
+                                    .changed();
+                            }
+                        });
+
+                    // images are picked from the registered ones
+                    if kind == "image" {
+                        egui::ComboBox::from_id_salt(self.id.with(("image", index)))
+                            .selected_text(value.as_str())
+                            .show_ui(ui, |ui| {
+                                for name in &image_names {
+                                    changed |= ui
+                                        .selectable_value(value, name.to_string(), name.as_str())
+                                        .changed();
+                                }
+                            });
+                    } else {
+                        let edit = egui::TextEdit::singleline(value).desired_width(220.);
+                        changed |= ui.add(edit).changed();
                     }
+
+                    if ui.small_button("✖").on_hover_text("Remove").clicked() {
+                        remove = Some(index);
+                    }
+                    ui.end_row();
                 }
-            })
+            });
+
+        if let Some(index) = swap {
+            self.data.swap(index, index + 1);
+            changed = true;
+        }
+        if let Some(index) = remove {
+            self.data.remove(index);
+            changed = true;
+        }
+        if ui.button("➕ Add item").clicked() {
+            self.data.push(("label".to_string(), String::new()));
+            changed = true;
+        }
+
+        self.edited |= changed;
     }
 }
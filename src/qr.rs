@@ -0,0 +1,126 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use typst::{
+    diag::{FileError, FileResult},
+    foundations::Bytes,
+};
+
+use crate::files::FileProvider;
+
+/// The url of the live cv, from the `url` of the popup data.
+///
+/// On the web it falls back to the address the cv is served from.
+pub fn cv_url(popup_data: Option<&toml::Table>) -> Option<String> {
+    let url = popup_data
+        .and_then(|data| data.get("url"))
+        .and_then(|url| url.as_str())
+        .map(String::from);
+
+    #[cfg(target_arch = "wasm32")]
+    let url = url.or_else(|| {
+        let location = web_sys::window()?.location();
+        Some(location.origin().ok()? + &location.pathname().ok()?)
+    });
+
+    url
+}
+
+/// The deep link to a block of the live cv, or the cv itself for an empty label.
+pub fn block_url(cv_url: &str, label: &str) -> String {
+    if label.is_empty() {
+        cv_url.to_string()
+    } else {
+        format!("{}#block={label}", cv_url.trim_end_matches('#'))
+    }
+}
+
+/// Encodes the text as a QR code, black on white as an svg.
+///
+/// The code is generated locally, no online service is involved.
+pub fn svg(text: &str) -> Result<String, String> {
+    let code = qrcode::QrCode::new(text.as_bytes())
+        .map_err(|e| format!("Could not create the QR code: {e}"))?;
+    Ok(code
+        .render::<qrcode::render::svg::Color<'_>>()
+        .min_dimensions(256, 256)
+        .build())
+}
+
+/// Adds QR codes to the files of a document: `qr.svg` links to the live cv and `qr/<label>.svg`
+/// to a block of it, so every label, `cv` included, names a block.
+///
+/// Files that exist in the wrapped files are read from there instead.
+pub struct QrCodes {
+    cv_url: String,
+    files: Arc<dyn FileProvider>,
+}
+
+impl QrCodes {
+    pub fn new(cv_url: impl Into<String>, files: Arc<dyn FileProvider>) -> Self {
+        Self {
+            cv_url: cv_url.into(),
+            files,
+        }
+    }
+}
+
+impl FileProvider for QrCodes {
+    fn read(&self, path: &Path) -> FileResult<Bytes> {
+        let error = match self.files.read(path) {
+            Ok(bytes) => return Ok(bytes),
+            Err(error) => error,
+        };
+
+        let label = if path == Path::new("qr.svg") {
+            ""
+        } else if let Some(label) = path
+            .strip_prefix("qr")
+            .ok()
+            .filter(|name| name.extension().is_some_and(|ext| ext == "svg"))
+            .and_then(|name| name.file_stem()?.to_str())
+        {
+            label
+        } else {
+            return Err(error);
+        };
+
+        svg(&block_url(&self.cv_url, label))
+            .map(|svg| Bytes::new(svg.into_bytes()))
+            .map_err(|e| FileError::Other(Some(e.into())))
+    }
+
+    fn list(&self, dir: &Path) -> Vec<PathBuf> {
+        self.files.list(dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::files::Memory;
+
+    #[test]
+    fn names_the_codes_of_the_cv_and_its_blocks() {
+        let mut files = Memory::default();
+        files.insert("qr/kept.svg", Bytes::new(b"kept".to_vec()));
+        let codes = QrCodes::new("https://example.org/cv/", Arc::new(files));
+        let read = |path: &str| codes.read(Path::new(path)).map(|bytes| bytes.to_vec());
+
+        assert_eq!(
+            read("qr.svg"),
+            Ok(svg("https://example.org/cv/").unwrap().into_bytes())
+        );
+        assert_eq!(
+            read("qr/cv.svg"),
+            Ok(svg("https://example.org/cv/#block=cv")
+                .unwrap()
+                .into_bytes())
+        );
+        assert_eq!(read("qr/kept.svg"), Ok(b"kept".to_vec()));
+        assert!(read("qr/cv.png").is_err());
+        assert!(read("other.svg").is_err());
+    }
+}
@@ -0,0 +1,514 @@
+use std::collections::HashSet;
+
+use serde_json::Value;
+
+use crate::popup::RESERVED_KEYS;
+
+/// A cv generated from a [JSON Resume](https://jsonresume.org/schema) file.
+pub struct ImportedCv {
+    /// The typst source, every entry of the resume is a grid with a label, so it is a block.
+    pub source: String,
+    /// The popup data of the blocks, by their label: highlights, links, images, the timeline,
+    /// tags and the contact details.
+    pub popup_data: toml::Table,
+}
+
+/// The typst functions and settings the generated cv starts with.
+const PREAMBLE: &str = r#"// generated from a JSON Resume file, every labelled grid is a block with the table of the same
+// name in the popup data
+#let dark = sys.inputs.at("theme", default: "light") == "dark"
+// the app shows the first page, so the cv is a single page of any length
+#set page(width: 21cm, height: auto, margin: 1.5cm, fill: none)
+#set text(size: 10pt, fill: if dark { luma(90%) } else { black })
+#set text(lang: sys.inputs.at("lang", default: "en"))
+#show heading: set block(above: 1.4em, below: 0.8em)
+
+#set par(spacing: 0.7em)
+#set block(spacing: 1.2em)
+
+// the block keeps the content of a grid in one group of the page, otherwise typst inlines small
+// grids and the app could not find them
+#let entry(title: "", subtitle: "", dates: "", body: "") = grid(block(width: 100%, {
+  strong(title)
+  if subtitle != "" [ --- #subtitle]
+  h(1fr)
+  emph(dates)
+  if body != "" {
+    parbreak()
+    body
+  }
+}))
+"#;
+
+/// An entry of a section of the resume.
+#[derive(Default)]
+struct Entry {
+    title: String,
+    subtitle: String,
+    start: String,
+    end: String,
+    body: String,
+    url: String,
+    /// Whether the entry spans a time, without an end date it is still ongoing.
+    range: bool,
+    /// Shown as labels in the popup.
+    highlights: Vec<String>,
+    tags: Vec<String>,
+}
+
+/// The sections of the resume in the order of the cv, with their key and title.
+const SECTIONS: [(&str, &str); 11] = [
+    ("work", "Work"),
+    ("volunteer", "Volunteering"),
+    ("education", "Education"),
+    ("projects", "Projects"),
+    ("awards", "Awards"),
+    ("certificates", "Certificates"),
+    ("publications", "Publications"),
+    ("skills", "Skills"),
+    ("languages", "Languages"),
+    ("interests", "Interests"),
+    ("references", "References"),
+];
+
+/// Generates the typst source and the popup data of a cv from a JSON Resume file.
+///
+/// Missing fields are left out, unknown fields are ignored.
+pub fn import(json: &str) -> Result<ImportedCv, String> {
+    let resume: Value =
+        serde_json::from_str(json).map_err(|e| format!("Could not read the resume: {e}"))?;
+    if !resume.is_object() {
+        return Err("The resume has to be a JSON object".to_string());
+    }
+
+    let mut source = PREAMBLE.to_string();
+    let mut popup_data = toml::Table::new();
+    let mut labels: HashSet<String> = RESERVED_KEYS.iter().map(|key| key.to_string()).collect();
+
+    let basics = &resume["basics"];
+    if basics.is_object() {
+        let label = unique_label("basics", &mut labels);
+        source.push_str(&basics_block(basics, &label));
+        insert_popup(&mut popup_data, label, basics_popup(basics));
+    }
+
+    for (key, title) in SECTIONS {
+        let entries: Vec<Entry> = list(&resume[key]).iter().map(|v| entry(key, v)).collect();
+        if entries.is_empty() {
+            continue;
+        }
+
+        source.push_str(&format!("\n= {title}\n"));
+        for (index, entry) in entries.iter().enumerate() {
+            // titles without latin letters or digits are numbered
+            let name = Some(slug(&entry.title))
+                .filter(|slug| !slug.is_empty())
+                .unwrap_or_else(|| (index + 1).to_string());
+            let label = unique_label(&format!("{key}-{name}"), &mut labels);
+            source.push_str(&entry_block(entry, &label));
+            insert_popup(&mut popup_data, label, entry_popup(entry, title));
+        }
+    }
+
+    Ok(ImportedCv { source, popup_data })
+}
+
+/// Reads an entry of the section `key`, the fields differ between the sections.
+fn entry(key: &str, v: &Value) -> Entry {
+    let strings = |key: &str| -> Vec<String> {
+        list(&v[key])
+            .iter()
+            .filter_map(|v| v.as_str().map(String::from))
+            .collect()
+    };
+    let mut entry = Entry {
+        start: text(v, "startDate"),
+        end: text(v, "endDate"),
+        url: text(v, "url"),
+        highlights: strings("highlights"),
+        range: ["work", "volunteer", "education", "projects"].contains(&key),
+        ..Default::default()
+    };
+
+    match key {
+        "work" => {
+            entry.title = first(v, &["name", "company"]);
+            entry.subtitle = text(v, "position");
+            entry.body = text(v, "summary");
+        }
+        "volunteer" => {
+            entry.title = text(v, "organization");
+            entry.subtitle = text(v, "position");
+            entry.body = text(v, "summary");
+        }
+        "education" => {
+            entry.title = text(v, "institution");
+            entry.subtitle = [text(v, "studyType"), text(v, "area")]
+                .into_iter()
+                .filter(|part| !part.is_empty())
+                .collect::<Vec<_>>()
+                .join(", ");
+            entry.body = text(v, "score");
+            entry.highlights.extend(strings("courses"));
+        }
+        "projects" => {
+            entry.title = text(v, "name");
+            entry.subtitle = strings("roles").join(", ");
+            entry.body = text(v, "description");
+            entry.tags = strings("keywords");
+        }
+        "awards" => {
+            entry.title = text(v, "title");
+            entry.subtitle = text(v, "awarder");
+            entry.start = text(v, "date");
+            entry.body = text(v, "summary");
+        }
+        "certificates" => {
+            entry.title = text(v, "name");
+            entry.subtitle = text(v, "issuer");
+            entry.start = text(v, "date");
+        }
+        "publications" => {
+            entry.title = text(v, "name");
+            entry.subtitle = text(v, "publisher");
+            entry.start = text(v, "releaseDate");
+            entry.body = text(v, "summary");
+        }
+        "skills" => {
+            entry.title = text(v, "name");
+            entry.subtitle = text(v, "level");
+            entry.body = strings("keywords").join(", ");
+            entry.tags = strings("keywords");
+        }
+        "languages" => {
+            entry.title = text(v, "language");
+            entry.subtitle = text(v, "fluency");
+        }
+        "interests" => {
+            entry.title = text(v, "name");
+            entry.body = strings("keywords").join(", ");
+        }
+        "references" => {
+            entry.title = text(v, "name");
+            entry.body = text(v, "reference");
+        }
+        _ => (),
+    }
+    entry
+}
+
+/// The header with the name, the contact line and the summary.
+fn basics_block(basics: &Value, label: &str) -> String {
+    let contact_line = [
+        text(basics, "email"),
+        text(basics, "phone"),
+        text(basics, "url"),
+        location(basics),
+    ]
+    .into_iter()
+    .filter(|part| !part.is_empty())
+    .collect::<Vec<_>>()
+    .join(" · ");
+
+    let mut paragraphs = vec![format!(
+        "  #text(size: 20pt, weight: \"bold\", {})\n",
+        string(&text(basics, "name"))
+    )];
+    for (part, style) in [
+        (text(basics, "label"), "size: 12pt, "),
+        (contact_line, ""),
+        (text(basics, "summary"), ""),
+    ] {
+        if !part.is_empty() {
+            paragraphs.push(format!("  #text({style}{})\n", string(&part)));
+        }
+    }
+
+    format!(
+        "\n#grid(block(width: 100%)[\n{}]) <{label}>\n",
+        paragraphs.join("\n")
+    )
+}
+
+/// The popup of the header: the picture, the profiles and the contact details.
+fn basics_popup(basics: &Value) -> toml::Table {
+    let mut items = Vec::new();
+    push_item(&mut items, "image", text(basics, "image"));
+    push_item(&mut items, "link", text(basics, "url"));
+    let profiles = list(&basics["profiles"]);
+    for profile in profiles {
+        push_item(&mut items, "link", text(profile, "url"));
+    }
+
+    let mut popup = toml::Table::new();
+    if !items.is_empty() {
+        popup.insert("items".to_string(), toml::Value::Array(items));
+    }
+
+    // the contact details can be saved as a vCard
+    let name = text(basics, "name");
+    if !name.is_empty() {
+        let mut contact = toml::Table::new();
+        let urls: Vec<String> = [text(basics, "url")]
+            .into_iter()
+            .chain(profiles.iter().map(|profile| text(profile, "url")))
+            .filter(|url| !url.is_empty())
+            .collect();
+        for (key, value) in [
+            ("name", name),
+            ("title", text(basics, "label")),
+            ("email", text(basics, "email")),
+            ("phone", text(basics, "phone")),
+            ("address", location(basics)),
+        ] {
+            if !value.is_empty() {
+                contact.insert(key.to_string(), toml::Value::String(value));
+            }
+        }
+        if !urls.is_empty() {
+            contact.insert("url".to_string(), urls.into());
+        }
+        popup.insert("contact".to_string(), toml::Value::Table(contact));
+    }
+    popup
+}
+
+fn entry_block(entry: &Entry, label: &str) -> String {
+    let dates = match (entry.start.as_str(), entry.end.as_str()) {
+        ("", _) => entry.end.clone(),
+        (start, "") if entry.range => format!("{start} – present"),
+        (start, "") => start.to_string(),
+        (start, end) => format!("{start} – {end}"),
+    };
+
+    let mut arguments = String::new();
+    for (name, value) in [
+        ("title", &entry.title),
+        ("subtitle", &entry.subtitle),
+        ("dates", &dates),
+        ("body", &entry.body),
+    ] {
+        if !value.is_empty() {
+            arguments.push_str(&format!("  {name}: {},\n", string(value)));
+        }
+    }
+    format!("\n#entry(\n{arguments}) <{label}>\n")
+}
+
+/// The popup of an entry: the highlights, the link, the date range for the timeline and the tags.
+fn entry_popup(entry: &Entry, section: &str) -> toml::Table {
+    let mut items = Vec::new();
+    for highlight in &entry.highlights {
+        push_item(&mut items, "label", highlight.clone());
+    }
+    push_item(&mut items, "link", entry.url.clone());
+
+    let mut popup = toml::Table::new();
+    if !items.is_empty() {
+        popup.insert("items".to_string(), toml::Value::Array(items));
+    }
+    if entry.range && !entry.start.is_empty() {
+        let mut timeline = toml::Table::new();
+        timeline.insert("start".to_string(), entry.start.clone().into());
+        if !entry.end.is_empty() {
+            timeline.insert("end".to_string(), entry.end.clone().into());
+        }
+        timeline.insert("title".to_string(), entry.title.clone().into());
+        timeline.insert("row".to_string(), section.into());
+        popup.insert("timeline".to_string(), toml::Value::Table(timeline));
+    }
+    if !entry.tags.is_empty() {
+        popup.insert("tags".to_string(), entry.tags.clone().into());
+    }
+    popup
+}
+
+/// Adds the popup of a block, blocks without anything to show get no popup.
+fn insert_popup(popup_data: &mut toml::Table, label: String, popup: toml::Table) {
+    if !popup.is_empty() {
+        popup_data.insert(label, toml::Value::Table(popup));
+    }
+}
+
+fn push_item(items: &mut Vec<toml::Value>, kind: &str, value: String) {
+    if value.is_empty() {
+        return;
+    }
+    let mut item = toml::Table::new();
+    item.insert(kind.to_string(), toml::Value::String(value));
+    items.push(toml::Value::Table(item));
+}
+
+/// The city, region and country of the location of the basics.
+fn location(basics: &Value) -> String {
+    let location = &basics["location"];
+    ["address", "city", "region", "countryCode"]
+        .into_iter()
+        .map(|key| text(location, key))
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn text(v: &Value, key: &str) -> String {
+    v[key].as_str().unwrap_or_default().trim().to_string()
+}
+
+fn first(v: &Value, keys: &[&str]) -> String {
+    keys.iter()
+        .map(|key| text(v, key))
+        .find(|value| !value.is_empty())
+        .unwrap_or_default()
+}
+
+fn list(v: &Value) -> &[Value] {
+    v.as_array().map(Vec::as_slice).unwrap_or_default()
+}
+
+/// A typst string literal, so the text of the resume is never read as markup.
+fn string(text: &str) -> String {
+    let mut literal = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '\\' => literal.push_str("\\\\"),
+            '"' => literal.push_str("\\\""),
+            '\n' => literal.push_str("\\n"),
+            '\r' => (),
+            '\t' => literal.push_str("\\t"),
+            _ => literal.push(c),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+/// A label made of lowercase letters, digits and dashes, e.g. `Acme Corp.` becomes `acme-corp`.
+fn slug(text: &str) -> String {
+    text.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// The label, with a number appended if it is taken already or a reserved key of the popup data.
+fn unique_label(label: &str, labels: &mut HashSet<String>) -> String {
+    let mut unique = label.to_string();
+    let mut number = 2;
+    while !labels.insert(unique.clone()) {
+        unique = format!("{label}-{number}");
+        number += 1;
+    }
+    unique
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RESUME: &str = r#"{
+        "basics": {
+            "name": "Jane \"JD\" Doe",
+            "label": "Engineer",
+            "email": "jane@example.org",
+            "profiles": [{ "url": "https://example.org/jane" }]
+        },
+        "work": [
+            {
+                "name": "Acme Corp.",
+                "position": "Dev",
+                "startDate": "2019-03",
+                "summary": "Quote \" backslash \\ hash #x dollar $x$ bracket [x] star *x*",
+                "highlights": ["Shipped it"],
+                "url": "https://acme.example.org"
+            },
+            { "name": "Acme Corp", "startDate": "2015", "endDate": "2018" },
+            { "name": "株式会社" }
+        ],
+        "skills": [{ "name": "Rust", "keywords": ["async", "egui"] }],
+        "languages": [{ "language": "日本語" }, { "language": "English" }]
+    }"#;
+
+    const LABELS: [&str; 7] = [
+        "basics",
+        "work-acme-corp",
+        "work-acme-corp-2",
+        "work-3",
+        "skills-rust",
+        "languages-1",
+        "languages-english",
+    ];
+
+    #[test]
+    fn labels_are_unique_and_not_reserved() {
+        let cv = import(RESUME).unwrap();
+        for label in LABELS {
+            assert!(cv.source.contains(&format!(") <{label}>\n")), "{label}");
+        }
+        for key in RESERVED_KEYS {
+            assert!(!cv.popup_data.contains_key(key), "{key}");
+        }
+
+        let mut labels = RESERVED_KEYS.iter().map(|key| key.to_string()).collect();
+        assert_eq!(unique_label("languages", &mut labels), "languages-2");
+        assert_eq!(unique_label("languages", &mut labels), "languages-3");
+    }
+
+    #[test]
+    fn fills_the_popup_tables() {
+        let cv = import(RESUME).unwrap();
+        let expected: toml::Table = toml::from_str(
+            r#"
+            [basics]
+            items = [{ link = "https://example.org/jane" }]
+            contact = { name = 'Jane "JD" Doe', title = "Engineer", email = "jane@example.org", url = ["https://example.org/jane"] }
+
+            [work-acme-corp]
+            items = [{ label = "Shipped it" }, { link = "https://acme.example.org" }]
+            timeline = { start = "2019-03", title = "Acme Corp.", row = "Work" }
+
+            [work-acme-corp-2]
+            timeline = { start = "2015", end = "2018", title = "Acme Corp", row = "Work" }
+
+            [skills-rust]
+            tags = ["async", "egui"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(cv.popup_data, expected);
+    }
+
+    #[test]
+    fn escapes_strings() {
+        assert_eq!(string(r#"a "b" \c"#), r#""a \"b\" \\c""#);
+        assert_eq!(string("line\nbreak\ttab\r"), r#""line\nbreak\ttab""#);
+    }
+
+    #[test]
+    #[cfg(not(target_arch = "wasm32"))]
+    fn every_entry_is_a_block() {
+        use crate::{document::DocumentPage, files::Memory};
+
+        let cv = import(RESUME).unwrap();
+        let (document, _) = DocumentPage::compile(
+            &cv.source,
+            &[],
+            std::sync::Arc::new(Memory::default()),
+            false,
+            false,
+        )
+        .unwrap();
+        assert_eq!(document.pages.len(), 1);
+
+        let blocks = DocumentPage::page_blocks(&document.pages[0]);
+        let labels: Vec<&str> = blocks.iter().map(|block| block.label.as_str()).collect();
+        assert_eq!(labels, LABELS);
+
+        // the text is never read as markup
+        let acme = &blocks[1].text;
+        assert!(
+            acme.contains("Quote \" backslash \\ hash #x dollar $x$ bracket [x] star *x*"),
+            "{acme}"
+        );
+    }
+}
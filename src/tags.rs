@@ -0,0 +1,152 @@
+use std::collections::BTreeMap;
+
+use egui::Context;
+
+use crate::overlay::Overlay;
+
+/// Reads the tags of a block from its table in the popup data and from a dictionary in the typst
+/// document like `#metadata((projects: ("python", "rust"))) <tags>`.
+pub fn block_tags(
+    popup_data: Option<&toml::Table>,
+    metadata: Option<&toml::Value>,
+    label: &str,
+) -> Vec<String> {
+    let from_data = popup_data
+        .and_then(|data| data.get(label))
+        .and_then(|block| block.get("tags"));
+    let from_metadata = metadata.and_then(|metadata| metadata.get(label));
+
+    let mut tags: Vec<String> = [from_data, from_metadata]
+        .into_iter()
+        .flatten()
+        .filter_map(|tags| tags.as_array())
+        .flatten()
+        .filter_map(|tag| tag.as_str().map(String::from))
+        .collect();
+    tags.sort();
+    tags.dedup();
+    tags
+}
+
+/// The tags selected in the tag bar, the blocks have to carry all or any of them.
+#[derive(Default)]
+pub struct TagFilter {
+    selected: Vec<String>,
+    match_all: bool,
+}
+
+impl TagFilter {
+    pub fn is_active(&self) -> bool {
+        !self.selected.is_empty()
+    }
+
+    /// Drops the selected tags no block carries anymore, e.g. after switching the variant, so the
+    /// filter can't hide every block without a tag bar to clear it.
+    pub fn retain_present(&mut self, areas: &[Overlay]) {
+        self.selected
+            .retain(|tag| areas.iter().any(|area| area.tags().contains(tag)));
+    }
+
+    /// Whether a block with the tags passes an active filter.
+    pub fn matches(&self, tags: &[String]) -> bool {
+        let mut selected = self.selected.iter();
+        if self.match_all {
+            selected.all(|tag| tags.contains(tag))
+        } else {
+            selected.any(|tag| tags.contains(tag))
+        }
+    }
+
+    /// Shows the tag bar in the top left corner, with every tag and the number of its blocks.
+    ///
+    /// Returns true if the selection changed. Nothing is shown if no block has tags.
+    pub fn show(&mut self, ctx: &Context, areas: &[Overlay]) -> bool {
+        let mut counts = BTreeMap::<&str, usize>::new();
+        for tag in areas.iter().flat_map(|area| area.tags()) {
+            *counts.entry(tag).or_default() += 1;
+        }
+        if counts.is_empty() {
+            return false;
+        }
+
+        let mut changed = false;
+        egui::Area::new(egui::Id::new("tag_bar"))
+            .anchor(egui::Align2::LEFT_TOP, [8., 8.])
+            .order(egui::Order::Foreground)
+            .show(ctx, |ui| {
+                ui.set_max_width(ctx.screen_rect().width() / 2.);
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.horizontal_wrapped(|ui| {
+                        for (tag, count) in counts {
+                            let selected = self.selected.iter().any(|s| s == tag);
+                            if ui
+                                .selectable_label(selected, format!("{tag} {count}"))
+                                .clicked()
+                            {
+                                if selected {
+                                    self.selected.retain(|s| s != tag);
+                                } else {
+                                    self.selected.push(tag.to_string());
+                                }
+                                changed = true;
+                            }
+                        }
+
+                        // the combination only matters with several tags
+                        if self.selected.len() > 1 {
+                            ui.separator();
+                            changed |= ui
+                                .selectable_value(&mut self.match_all, false, "Any")
+                                .on_hover_text("Blocks with any of the tags")
+                                .changed();
+                            changed |= ui
+                                .selectable_value(&mut self.match_all, true, "All")
+                                .on_hover_text("Blocks with all of the tags")
+                                .changed();
+                        }
+
+                        if self.is_active() {
+                            ui.separator();
+                            if ui.button("✖").on_hover_text("Clear the filter").clicked() {
+                                self.selected.clear();
+                                changed = true;
+                            }
+                        }
+                    });
+                });
+            });
+
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::theme::Theme;
+
+    fn area(label: &str, tags: &[&str]) -> Overlay {
+        Overlay::new(
+            egui::Rect::ZERO,
+            label.to_string(),
+            String::new(),
+            Theme::default().overlay,
+            None,
+            tags.iter().map(|tag| tag.to_string()).collect(),
+        )
+    }
+
+    #[test]
+    fn drops_the_tags_of_blocks_that_are_gone() {
+        let mut filter = TagFilter {
+            selected: vec!["rust".to_string(), "python".to_string()],
+            match_all: false,
+        };
+        filter.retain_present(&[area("work", &["rust"]), area("skills", &[])]);
+        assert_eq!(filter.selected, ["rust"]);
+
+        // a variant without tagged blocks turns the filter off
+        filter.retain_present(&[area("work", &[])]);
+        assert!(!filter.is_active());
+    }
+}
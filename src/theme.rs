@@ -0,0 +1,210 @@
+use egui::{Color32, CornerRadius, Frame, Margin, Shadow, Stroke};
+
+/// Styling of the overlays and popups.
+///
+/// The defaults can be changed with a `[theme]` table in `popup.toml` or a `<theme>` metadata in the
+/// typst document, and each block can override them with its own `theme` table. A nested `dark` or
+/// `light` table only applies in that mode.
+#[derive(Clone, Debug)]
+pub struct Theme {
+    pub overlay: OverlayStyle,
+    pub popup: PopupStyle,
+    dark_mode: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct OverlayStyle {
+    pub stroke: Color32,
+    pub stroke_width: f32,
+    pub hover_stroke: Color32,
+    pub hover_stroke_width: f32,
+    pub hover_fill: Color32,
+    pub corner_radius: f32,
+    /// Only draw the outline of a block while it is hovered.
+    pub hide_until_hover: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct PopupStyle {
+    pub fill: Color32,
+    pub stroke: Color32,
+    pub stroke_width: f32,
+    pub corner_radius: u8,
+    pub inner_margin: i8,
+    pub shadow: Color32,
+    pub shadow_offset: [i8; 2],
+    pub shadow_blur: u8,
+    pub shadow_spread: u8,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            overlay: OverlayStyle {
+                stroke: Color32::from_hex("#aaaaaa").unwrap(),
+                stroke_width: 0.5,
+                hover_stroke: Color32::from_hex("#09a7cb").unwrap(),
+                hover_stroke_width: 2.,
+                hover_fill: Color32::from_hex("#09a7cb11").unwrap(),
+                corner_radius: 5.,
+                hide_until_hover: false,
+            },
+            popup: PopupStyle {
+                fill: Color32::from_hex("#ffffff").unwrap(),
+                stroke: Color32::from_hex("#266590").unwrap(),
+                stroke_width: 2.,
+                corner_radius: 8,
+                inner_margin: 2,
+                shadow: Color32::from_hex("#000000aa").unwrap(),
+                shadow_offset: [10, 10],
+                shadow_blur: 20,
+                shadow_spread: 10,
+            },
+            dark_mode: false,
+        }
+    }
+}
+
+impl Theme {
+    /// The default theme for dark mode.
+    pub fn dark() -> Self {
+        let mut theme = Self::default();
+        theme.overlay.stroke = Color32::from_hex("#555555").unwrap();
+        theme.overlay.hover_fill = Color32::from_hex("#09a7cb22").unwrap();
+        theme.popup.fill = Color32::from_hex("#1b1b1b").unwrap();
+        theme.popup.stroke = Color32::from_hex("#4a9bd1").unwrap();
+        theme
+    }
+
+    /// Builds the global theme, the typst metadata takes precedence over the popup data file.
+    pub fn load(
+        popup_data: Option<&toml::Table>,
+        metadata: Option<&toml::Value>,
+        dark_mode: bool,
+    ) -> Self {
+        let mut theme = if dark_mode {
+            Self::dark()
+        } else {
+            Self::default()
+        };
+        theme.dark_mode = dark_mode;
+
+        if let Some(table) = popup_data
+            .and_then(|data| data.get("theme"))
+            .and_then(|v| v.as_table())
+        {
+            theme.apply(table);
+        }
+        if let Some(table) = metadata.and_then(|v| v.as_table()) {
+            theme.apply(table);
+        }
+
+        theme
+    }
+
+    /// Returns the theme for a single block with the overrides of its `theme` table applied.
+    pub fn for_block(&self, popup_data: Option<&toml::Table>, label: &str) -> Self {
+        let mut theme = self.clone();
+        if let Some(table) = popup_data
+            .and_then(|data| data.get(label))
+            .and_then(|block| block.get("theme"))
+            .and_then(|v| v.as_table())
+        {
+            theme.apply(table);
+        }
+        theme
+    }
+
+    /// Applies the styles of the table, followed by the ones for the current mode.
+    fn apply(&mut self, table: &toml::Table) {
+        self.apply_styles(table);
+
+        let mode = if self.dark_mode { "dark" } else { "light" };
+        if let Some(table) = table.get(mode).and_then(|v| v.as_table()) {
+            self.apply_styles(table);
+        }
+    }
+
+    fn apply_styles(&mut self, table: &toml::Table) {
+        if let Some(overlay) = table.get("overlay").and_then(|v| v.as_table()) {
+            let style = &mut self.overlay;
+            read_color(overlay, "stroke", &mut style.stroke);
+            read_float(overlay, "stroke_width", &mut style.stroke_width);
+            read_color(overlay, "hover_stroke", &mut style.hover_stroke);
+            read_float(overlay, "hover_stroke_width", &mut style.hover_stroke_width);
+            read_color(overlay, "hover_fill", &mut style.hover_fill);
+            read_float(overlay, "corner_radius", &mut style.corner_radius);
+            if let Some(hide) = overlay.get("hide_until_hover").and_then(|v| v.as_bool()) {
+                style.hide_until_hover = hide;
+            }
+        }
+
+        if let Some(popup) = table.get("popup").and_then(|v| v.as_table()) {
+            let style = &mut self.popup;
+            read_color(popup, "fill", &mut style.fill);
+            read_color(popup, "stroke", &mut style.stroke);
+            read_float(popup, "stroke_width", &mut style.stroke_width);
+            read_int(popup, "corner_radius", &mut style.corner_radius);
+            read_int(popup, "inner_margin", &mut style.inner_margin);
+            read_color(popup, "shadow", &mut style.shadow);
+            read_int(popup, "shadow_blur", &mut style.shadow_blur);
+            read_int(popup, "shadow_spread", &mut style.shadow_spread);
+            if let Some([x, y]) = popup
+                .get("shadow_offset")
+                .and_then(|v| v.as_array())
+                .map(|offset| offset.as_slice())
+            {
+                read_int_value(x, &mut style.shadow_offset[0]);
+                read_int_value(y, &mut style.shadow_offset[1]);
+            }
+        }
+    }
+}
+
+impl PopupStyle {
+    pub fn frame(&self) -> Frame {
+        Frame {
+            inner_margin: Margin::same(self.inner_margin),
+            outer_margin: Margin::ZERO,
+            corner_radius: CornerRadius::same(self.corner_radius),
+            shadow: Shadow {
+                color: self.shadow,
+                offset: self.shadow_offset,
+                blur: self.shadow_blur,
+                spread: self.shadow_spread,
+            },
+            fill: self.fill,
+            stroke: Stroke::new(self.stroke_width, self.stroke),
+        }
+    }
+}
+
+fn read_color(table: &toml::Table, key: &str, target: &mut Color32) {
+    if let Some(color) = table
+        .get(key)
+        .and_then(|v| v.as_str())
+        .and_then(|hex| Color32::from_hex(hex).ok())
+    {
+        *target = color;
+    }
+}
+
+fn read_float(table: &toml::Table, key: &str, target: &mut f32) {
+    match table.get(key) {
+        Some(toml::Value::Float(value)) => *target = *value as f32,
+        Some(toml::Value::Integer(value)) => *target = *value as f32,
+        _ => (),
+    }
+}
+
+fn read_int<T: TryFrom<i64>>(table: &toml::Table, key: &str, target: &mut T) {
+    if let Some(value) = table.get(key) {
+        read_int_value(value, target);
+    }
+}
+
+fn read_int_value<T: TryFrom<i64>>(value: &toml::Value, target: &mut T) {
+    if let Some(value) = value.as_integer().and_then(|v| T::try_from(v).ok()) {
+        *target = value;
+    }
+}
@@ -0,0 +1,296 @@
+use egui::{Align2, FontId, Pos2, Rect, Sense, Ui, Vec2, WidgetInfo, WidgetType};
+
+const LANE_HEIGHT: f32 = 22.;
+const AXIS_HEIGHT: f32 = 18.;
+
+/// A block with a date range, shown as a bar in the timeline.
+pub struct Entry {
+    label: String,
+    title: String,
+    row: String,
+    start: f32,
+    /// `None` for ongoing entries, which end today.
+    end: Option<f32>,
+    dates: String,
+}
+
+/// Reads the date ranges of the blocks with the given labels, in that order.
+///
+/// A range is given in a `timeline` table of the block in the popup data or in a dictionary in
+/// the typst document like `#metadata((job: (start: "2019-03", end: "present"))) <timeline>`.
+pub fn entries(
+    popup_data: Option<&toml::Table>,
+    metadata: Option<&toml::Value>,
+    labels: &[String],
+) -> Vec<Entry> {
+    labels
+        .iter()
+        .filter_map(|label| {
+            let range = metadata
+                .and_then(|metadata| metadata.get(label))
+                .or_else(|| {
+                    popup_data
+                        .and_then(|data| data.get(label))
+                        .and_then(|block| block.get("timeline"))
+                })?
+                .as_table()?;
+
+            let text = |key: &str| match range.get(key)? {
+                toml::Value::String(v) => Some(v.clone()),
+                toml::Value::Integer(v) => Some(v.to_string()),
+                toml::Value::Datetime(v) => v.date.map(|date| date.to_string()),
+                _ => None,
+            };
+            let start_text = text("start")?;
+            let end_text = text("end").unwrap_or("present".to_string());
+
+            // only a missing end or `present` is ongoing, a typo skips the entry
+            let end = if end_text.trim() == "present" {
+                None
+            } else {
+                Some(parse_date(&end_text)?)
+            };
+
+            Some(Entry {
+                label: label.clone(),
+                title: text("title").unwrap_or(label.clone()),
+                row: text("row").unwrap_or_default(),
+                start: parse_date(&start_text)?,
+                end,
+                dates: format!("{start_text} – {end_text}"),
+            })
+        })
+        .collect()
+}
+
+/// Parses `2019`, `2019-03` or `2019-03-15` as a fractional year.
+fn parse_date(date: &str) -> Option<f32> {
+    let mut parts = date.trim().split('-');
+    let year: u16 = parts.next()?.parse().ok()?;
+    let month: u8 = parts.next().map_or(Ok(1), str::parse).ok()?;
+    let day: u8 = parts.next().map_or(Ok(1), str::parse).ok()?;
+    if parts.next().is_some() || !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    Some(year as f32 + (month as f32 - 1.) / 12.)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn current_year() -> f32 {
+    let seconds = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64();
+    (1970. + seconds / (365.2425 * 86400.)) as f32
+}
+
+#[cfg(target_arch = "wasm32")]
+fn current_year() -> f32 {
+    (1970. + js_sys::Date::now() / (365.2425 * 86400. * 1000.)) as f32
+}
+
+/// Assigns every entry to a lane, overlapping entries of the same row are stacked.
+///
+/// Returns the lane of each entry and the first lane of each row.
+fn lanes(entries: &[Entry], now: f32) -> (Vec<usize>, Vec<(&str, usize)>) {
+    let mut rows: Vec<&str> = Vec::new();
+    for entry in entries {
+        if !rows.contains(&entry.row.as_str()) {
+            rows.push(&entry.row);
+        }
+    }
+
+    let mut lane_of_entry = vec![0; entries.len()];
+    let mut first_lanes = Vec::new();
+    let mut lane_count = 0;
+    for row in rows {
+        // the end of the last entry in each lane of the row
+        let mut lane_ends: Vec<f32> = Vec::new();
+        let mut order: Vec<usize> = (0..entries.len())
+            .filter(|&i| entries[i].row == row)
+            .collect();
+        order.sort_by(|&a, &b| entries[a].start.total_cmp(&entries[b].start));
+
+        for i in order {
+            let entry = &entries[i];
+            let lane = match lane_ends.iter().position(|end| *end <= entry.start) {
+                Some(lane) => lane,
+                None => {
+                    lane_ends.push(0.);
+                    lane_ends.len() - 1
+                }
+            };
+            lane_ends[lane] = entry.end.unwrap_or(now);
+            lane_of_entry[i] = lane_count + lane;
+        }
+
+        first_lanes.push((row, lane_count));
+        lane_count += lane_ends.len();
+    }
+
+    (lane_of_entry, first_lanes)
+}
+
+/// Shows the entries as bars on a time axis, returns the label of a clicked bar.
+pub fn show(ui: &mut Ui, entries: &[Entry]) -> Option<String> {
+    let now = current_year();
+    let first = entries
+        .iter()
+        .map(|entry| entry.start)
+        .fold(f32::INFINITY, f32::min)
+        .floor();
+    let last = entries
+        .iter()
+        .map(|entry| entry.end.unwrap_or(now))
+        .fold(first + 1., f32::max)
+        .ceil();
+    let (lane_of_entry, rows) = lanes(entries, now);
+    let lane_count = lane_of_entry.iter().max().map_or(0, |lane| lane + 1);
+
+    // the row names get their own column if there are any
+    let row_width = if rows.iter().any(|(row, _)| !row.is_empty()) {
+        90.
+    } else {
+        0.
+    };
+    let size = Vec2::new(
+        ui.available_width(),
+        lane_count as f32 * LANE_HEIGHT + AXIS_HEIGHT,
+    );
+    let (rect, _) = ui.allocate_exact_size(size, Sense::hover());
+    let bars = Rect::from_min_max(
+        rect.min + Vec2::new(row_width, 0.),
+        rect.max - Vec2::new(0., AXIS_HEIGHT),
+    );
+    let x = |year: f32| egui::emath::remap(year, first..=last, bars.x_range());
+    let lane_top = |lane: usize| bars.top() + lane as f32 * LANE_HEIGHT;
+
+    let visuals = ui.visuals().clone();
+    let painter = ui.painter_at(rect);
+    let font = FontId::proportional(12.);
+
+    // the year axis, with fewer ticks if the years get too narrow
+    let step = (40. / (bars.width() / (last - first))).ceil().max(1.);
+    let mut year = first;
+    while year <= last {
+        painter.vline(
+            x(year),
+            bars.y_range(),
+            visuals.widgets.noninteractive.bg_stroke,
+        );
+        painter.text(
+            Pos2::new(x(year), bars.bottom() + 2.),
+            Align2::CENTER_TOP,
+            year.to_string(),
+            font.clone(),
+            visuals.weak_text_color(),
+        );
+        year += step;
+    }
+
+    for (row, lane) in rows {
+        painter.text(
+            Pos2::new(rect.left(), lane_top(lane) + LANE_HEIGHT / 2.),
+            Align2::LEFT_CENTER,
+            row,
+            font.clone(),
+            visuals.text_color(),
+        );
+    }
+
+    let mut clicked = None;
+    for (entry, lane) in entries.iter().zip(lane_of_entry) {
+        let bar = Rect::from_x_y_ranges(
+            x(entry.start)..=x(entry.end.unwrap_or(now)).max(x(entry.start) + 4.),
+            lane_top(lane) + 2.0..=lane_top(lane) + LANE_HEIGHT - 2.,
+        );
+        let response = ui
+            .interact(
+                bar,
+                ui.id().with(("timeline", &entry.label)),
+                Sense::click(),
+            )
+            .on_hover_text(&entry.dates);
+        response.widget_info(|| {
+            WidgetInfo::labeled(
+                WidgetType::Button,
+                true,
+                format!("{}, {}", entry.title, entry.dates),
+            )
+        });
+
+        let fill = if response.hovered() || response.has_focus() {
+            visuals.selection.bg_fill
+        } else {
+            visuals.selection.bg_fill.gamma_multiply(0.6)
+        };
+        painter.rect_filled(bar, 3., fill);
+        painter.with_clip_rect(bar.intersect(rect)).text(
+            bar.left_center() + Vec2::new(4., 0.),
+            Align2::LEFT_CENTER,
+            &entry.title,
+            font.clone(),
+            visuals.selection.stroke.color,
+        );
+
+        if response.clicked() {
+            clicked = Some(entry.label.clone());
+        }
+    }
+
+    clicked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_years_months_and_days() {
+        assert_eq!(parse_date("2019"), Some(2019.));
+        assert_eq!(parse_date("2019-01"), Some(2019.));
+        assert_eq!(parse_date(" 2019-07 "), Some(2019.5));
+        assert_eq!(parse_date("2019-12-31"), Some(2019. + 11. / 12.));
+    }
+
+    #[test]
+    fn rejects_invalid_dates() {
+        for date in [
+            "",
+            "present",
+            "Dec 2019",
+            "2019-1x",
+            "2020-00",
+            "2020-13",
+            "2020-01-00",
+            "2020-01-32",
+            "2020-1-1-garbage",
+            "-2020",
+        ] {
+            assert_eq!(parse_date(date), None, "{date}");
+        }
+    }
+
+    fn entry_of(timeline: &str) -> Option<Entry> {
+        let data: toml::Table = toml::from_str(&format!("[job]\ntimeline = {timeline}")).unwrap();
+        entries(Some(&data), None, &["job".to_string()]).pop()
+    }
+
+    #[test]
+    fn only_a_missing_end_or_present_is_ongoing() {
+        assert_eq!(entry_of(r#"{ start = "2019" }"#).unwrap().end, None);
+        assert_eq!(
+            entry_of(r#"{ start = "2019", end = "present" }"#)
+                .unwrap()
+                .end,
+            None
+        );
+        assert_eq!(
+            entry_of(r#"{ start = "2019", end = "2020" }"#).unwrap().end,
+            Some(2020.)
+        );
+        assert!(entry_of(r#"{ start = "2019", end = "2019-1x" }"#).is_none());
+        assert!(entry_of(r#"{ start = "2019", end = "Dec 2019" }"#).is_none());
+        assert!(entry_of(r#"{ start = 2019-03-15, end = 2020-01-01 }"#).is_some());
+    }
+}
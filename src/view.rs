@@ -0,0 +1,66 @@
+use egui::{Pos2, Rect, Vec2};
+
+/// Zoom and pan of the page on the canvas.
+///
+/// Page coordinates are the coordinates of the page fitted to the canvas, which is the zoom level 1.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct View {
+    pub zoom: f32,
+    pub pan: Vec2,
+    /// The top left corner of the canvas on the screen.
+    pub origin: Pos2,
+}
+
+impl Default for View {
+    fn default() -> Self {
+        Self {
+            zoom: 1.,
+            pan: Vec2::ZERO,
+            origin: Pos2::ZERO,
+        }
+    }
+}
+
+impl View {
+    pub const MIN_ZOOM: f32 = 0.25;
+    pub const MAX_ZOOM: f32 = 8.;
+
+    pub fn transform_pos(&self, pos: Pos2) -> Pos2 {
+        self.origin + pos.to_vec2() * self.zoom + self.pan
+    }
+
+    pub fn transform_rect(&self, rect: Rect) -> Rect {
+        Rect::from_min_max(self.transform_pos(rect.min), self.transform_pos(rect.max))
+    }
+
+    /// Maps a position on the canvas back to page coordinates.
+    pub fn inverse_pos(&self, pos: Pos2) -> Pos2 {
+        ((pos - self.origin - self.pan) / self.zoom).to_pos2()
+    }
+
+    /// Zooms by `factor` while keeping the page position under `center` in place.
+    pub fn zoom_around(&mut self, center: Pos2, factor: f32) {
+        let zoom = (self.zoom * factor).clamp(Self::MIN_ZOOM, Self::MAX_ZOOM);
+        let page_pos = self.inverse_pos(center);
+        self.zoom = zoom;
+        self.pan = center - self.origin - page_pos.to_vec2() * zoom;
+    }
+
+    /// Pans the view so that `rect` on the canvas is within `viewport`, preferring its top left corner.
+    pub fn reveal(&mut self, rect: Rect, viewport: Rect) {
+        let mut delta = Vec2::ZERO;
+        if rect.right() > viewport.right() {
+            delta.x = viewport.right() - rect.right();
+        }
+        if rect.left() + delta.x < viewport.left() {
+            delta.x = viewport.left() - rect.left();
+        }
+        if rect.bottom() > viewport.bottom() {
+            delta.y = viewport.bottom() - rect.bottom();
+        }
+        if rect.top() + delta.y < viewport.top() {
+            delta.y = viewport.top() - rect.top();
+        }
+        self.pan += delta;
+    }
+}